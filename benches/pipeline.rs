@@ -0,0 +1,127 @@
+//! Throughput of each pipeline stage over representative documents.
+//!
+//! Run with `cargo bench`; pass a filter such as `cargo bench -- wide_table`
+//! to run one corpus. Every stage reports bytes of input per second.
+
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use fractured_json_rs::computed::ItemRef;
+use fractured_json_rs::transform::{parse, transform};
+use fractured_json_rs::{format_computed, format_jsonc, FracturedJsonOptions};
+use std::fmt::Write;
+
+/// A short settings file with comments, like an editor or tool config.
+fn small_config() -> String {
+    r#"{
+    // Editor settings
+    "editor.fontSize": 14,
+    "editor.rulers": [80, 120],
+    "editor.tabSize": 4, /* spaces */
+    "files.exclude": {"**/.git": true, "**/node_modules": true, "**/target": true},
+    "search.useIgnoreFiles": true,
+    "terminal": {"shell": "/bin/zsh", "args": ["-l"], "env": {"LANG": "en_US.UTF-8"}},
+    // Language overrides
+    "[rust]": {"editor.formatOnSave": true, "editor.defaultFormatter": "rust-lang.rust-analyzer"}
+}"#
+    .to_string()
+}
+
+/// An array of uniform records that lays out as a table.
+fn wide_table(rows: usize) -> String {
+    let mut out = String::from("[\n");
+    for i in 0..rows {
+        let sep = if i + 1 < rows { "," } else { "" };
+        writeln!(
+            out,
+            r#"  {{"id": {i}, "name": "user{i}", "email": "user{i}@example.com", "active": {}, "score": {}.{}}}{sep}"#,
+            i % 3 == 0,
+            i * 7 % 1000,
+            i % 100,
+        )
+        .unwrap();
+    }
+    out.push(']');
+    out
+}
+
+/// Alternating objects and arrays nested `depth` levels deep.
+fn deep_nesting(depth: usize) -> String {
+    let mut out = String::new();
+    for i in 0..depth {
+        if i % 2 == 0 {
+            write!(out, r#"{{"level": {i}, "child": "#).unwrap();
+        } else {
+            write!(out, "[{i}, ").unwrap();
+        }
+    }
+    out.push_str("null");
+    for i in (0..depth).rev() {
+        out.push(if i % 2 == 0 { '}' } else { ']' });
+    }
+    out
+}
+
+/// One long array of numbers that lays out as compact rows.
+fn compact_numbers(count: usize) -> String {
+    let numbers: Vec<String> = (0..count)
+        .map(|i| format!("{}.{:02}", (i * 7919) % 10_000, i % 100))
+        .collect();
+    format!("[{}]", numbers.join(", "))
+}
+
+/// JSONC where most properties carry prefix, middle or postfix comments.
+fn comment_heavy(props: usize) -> String {
+    let mut out = String::from("{\n");
+    for i in 0..props {
+        let sep = if i + 1 < props { "," } else { "" };
+        match i % 3 {
+            0 => writeln!(out, "  // property {i}\n  \"key{i}\": {i}{sep}").unwrap(),
+            1 => writeln!(out, "  \"key{i}\": /* inline {i} */ \"value {i}\"{sep}").unwrap(),
+            _ => writeln!(out, "  /* block {i} */ \"key{i}\": [{i}, {}]{sep}", i + 1).unwrap(),
+        }
+    }
+    out.push('}');
+    out
+}
+
+fn bench_corpus(c: &mut Criterion, name: &str, input: &str) {
+    let options = FracturedJsonOptions::default();
+    let mut group = c.benchmark_group(name);
+    group.throughput(Throughput::Bytes(input.len() as u64));
+
+    group.bench_function("parse", |b| b.iter(|| parse(black_box(input)).unwrap()));
+
+    group.bench_function("transform", |b| {
+        b.iter_batched(
+            || parse(input).unwrap(),
+            |parsed| transform(input, parsed),
+            BatchSize::LargeInput,
+        )
+    });
+
+    let item = transform(input, parse(input).unwrap());
+    group.bench_function("from_root", |b| {
+        b.iter(|| ItemRef::from_root(black_box(&item), &options))
+    });
+
+    let computed = ItemRef::from_root(&item, &options);
+    group.bench_function("format", |b| {
+        b.iter(|| format_computed(black_box(&computed), &options))
+    });
+
+    group.bench_function("end_to_end", |b| {
+        b.iter(|| format_jsonc(black_box(input), &options).unwrap())
+    });
+
+    group.finish();
+}
+
+fn pipeline(c: &mut Criterion) {
+    bench_corpus(c, "small_config", &small_config());
+    bench_corpus(c, "wide_table", &wide_table(5_000));
+    bench_corpus(c, "deep_nesting", &deep_nesting(200));
+    bench_corpus(c, "compact_numbers", &compact_numbers(100_000));
+    bench_corpus(c, "comment_heavy", &comment_heavy(5_000));
+}
+
+criterion_group!(benches, pipeline);
+criterion_main!(benches);
@@ -0,0 +1,263 @@
+//! RFC 8785 JSON Canonicalization Scheme (JCS): byte-exact output for
+//! hashing and signing.
+
+use crate::diagnostics::{Diagnostic, DiagnosticCode};
+use crate::error::{FracturedJsonError, Result};
+use crate::formatter::escape_string;
+use crate::transform::{self, transform};
+use crate::types::{JsonItem, JsonItemType};
+use std::fmt::Write;
+
+/// Parses `input` and writes it in canonical form.
+///
+/// Comments are dropped, object properties are sorted by their UTF-16 code
+/// units, numbers are written the way ECMAScript prints them, and no
+/// whitespace is added. Fails on numbers outside the IEEE 754 double range
+/// and on duplicate property names, which have no canonical form.
+pub fn canonicalize(input: &str) -> Result<String> {
+    let item = transform(input, transform::parse(input)?);
+    canonicalize_item(&item)
+}
+
+/// Writes `item` in canonical form. See [`canonicalize`].
+pub fn canonicalize_item(item: &JsonItem) -> Result<String> {
+    Ok(canonicalize_item_with_diagnostics(item)?.0)
+}
+
+/// Like [`canonicalize_item`], also reporting the numbers whose value
+/// changed on the way through a double, such as integers beyond 2^53.
+pub fn canonicalize_item_with_diagnostics(item: &JsonItem) -> Result<(String, Vec<Diagnostic>)> {
+    let mut out = String::new();
+    let mut diagnostics = Vec::new();
+    write_item(item, &mut out, &mut diagnostics)?;
+    Ok((out, diagnostics))
+}
+
+fn write_item(item: &JsonItem, out: &mut String, diagnostics: &mut Vec<Diagnostic>) -> Result<()> {
+    match item.item_type {
+        JsonItemType::Array => {
+            out.push('[');
+            for (i, child) in values(item).enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_item(child, out, diagnostics)?;
+            }
+            out.push(']');
+        }
+        JsonItemType::Object => {
+            let mut properties: Vec<&JsonItem> = values(item).collect();
+            properties.sort_by(|a, b| a.name.encode_utf16().cmp(b.name.encode_utf16()));
+            if let Some(pair) = properties.windows(2).find(|p| p[0].name == p[1].name) {
+                return Err(FracturedJsonError::FormattingError(format!(
+                    "duplicate key `{}` has no canonical form",
+                    pair[0].name
+                )));
+            }
+
+            out.push('{');
+            for (i, property) in properties.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_string(&property.name, out);
+                out.push(':');
+                write_item(property, out, diagnostics)?;
+            }
+            out.push('}');
+        }
+        JsonItemType::String => write_string(&item.value, out),
+        JsonItemType::Number => {
+            let start = out.len();
+            write_number(&item.value, out)?;
+            let written = &out[start..];
+            if decimal_value(written) != decimal_value(&item.value) {
+                diagnostics.push(Diagnostic::warning(
+                    DiagnosticCode::PrecisionLost,
+                    item.input_position,
+                    format!(
+                        "number `{}` written as `{}`, the nearest double",
+                        item.value, written
+                    ),
+                ));
+            }
+        }
+        JsonItemType::True => out.push_str("true"),
+        JsonItemType::False => out.push_str("false"),
+        JsonItemType::Null => out.push_str("null"),
+        JsonItemType::LineComment | JsonItemType::BlockComment | JsonItemType::BlankLine => {}
+    }
+    Ok(())
+}
+
+/// Children that are values rather than comments.
+fn values<'a, 'src>(item: &'a JsonItem<'src>) -> impl Iterator<Item = &'a JsonItem<'src>> {
+    item.children
+        .iter()
+        .filter(|c| !c.item_type.is_comment_or_blank())
+}
+
+fn write_string(s: &str, out: &mut String) {
+    out.push('"');
+    out.push_str(&escape_string(s));
+    out.push('"');
+}
+
+/// Writes `text` as ECMAScript's `Number.prototype.toString` would print
+/// the double it denotes.
+fn write_number(text: &str, out: &mut String) -> Result<()> {
+    let value: f64 = text
+        .parse()
+        .ok()
+        .filter(|v: &f64| v.is_finite())
+        .ok_or_else(|| {
+            FracturedJsonError::FormattingError(format!(
+                "number `{}` is not representable in canonical JSON",
+                text
+            ))
+        })?;
+
+    if value == 0.0 {
+        out.push('0');
+        return Ok(());
+    }
+    if value < 0.0 {
+        out.push('-');
+    }
+
+    // `{:e}` gives the shortest digits that round-trip, as in "1.2345e-7"
+    let scientific = format!("{:e}", value.abs());
+    let (mantissa, exponent) = scientific.split_once('e').unwrap_or((&scientific, "0"));
+    let digits: String = mantissa.chars().filter(|c| *c != '.').collect();
+    let exponent: i32 = exponent.parse().unwrap_or(0);
+    let k = digits.len() as i32;
+    // The value is 0.digits * 10^n
+    let n = exponent + 1;
+
+    if k <= n && n <= 21 {
+        out.push_str(&digits);
+        out.extend(std::iter::repeat_n('0', (n - k) as usize));
+    } else if 0 < n && n <= 21 {
+        let (integer, fraction) = digits.split_at(n as usize);
+        out.push_str(integer);
+        out.push('.');
+        out.push_str(fraction);
+    } else if -6 < n && n <= 0 {
+        out.push_str("0.");
+        out.extend(std::iter::repeat_n('0', (-n) as usize));
+        out.push_str(&digits);
+    } else {
+        let (first, rest) = digits.split_at(1);
+        out.push_str(first);
+        if !rest.is_empty() {
+            out.push('.');
+            out.push_str(rest);
+        }
+        let sign = if n - 1 < 0 { '-' } else { '+' };
+        let _ = write!(out, "e{}{}", sign, (n - 1).abs());
+    }
+    Ok(())
+}
+
+/// The exact value a JSON number denotes, as its significant digits `d`
+/// and exponent `n` such that the magnitude is `0.d × 10^n`. Signs of zero
+/// aren't told apart.
+fn decimal_value(text: &str) -> (bool, String, i64) {
+    let (negative, text) = match text.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, text),
+    };
+    let (mantissa, exponent) = match text.find(['e', 'E']) {
+        Some(at) => (&text[..at], text[at + 1..].parse().unwrap_or(0)),
+        None => (text, 0),
+    };
+    let (integer, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let digits = format!("{}{}", integer, fraction);
+    let significant = digits.trim_start_matches('0');
+    let point = integer.len() as i64 + exponent - (digits.len() - significant.len()) as i64;
+    let significant = significant.trim_end_matches('0');
+    if significant.is_empty() {
+        return (false, String::new(), 0);
+    }
+    (negative, significant.to_string(), point)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn number(text: &str) -> String {
+        let mut out = String::new();
+        write_number(text, &mut out).unwrap();
+        out
+    }
+
+    #[test]
+    fn test_numbers_in_ecmascript_form() {
+        // Including the examples from RFC 8785 appendix B
+        for (input, expected) in [
+            ("0", "0"),
+            ("-0", "0"),
+            ("1E1", "10"),
+            ("1.50", "1.5"),
+            ("100", "100"),
+            ("1e21", "1e+21"),
+            ("123456789012345680000", "123456789012345680000"),
+            ("9007199254740992", "9007199254740992"),
+            ("295147905179352830000", "295147905179352830000"),
+            ("0.000001", "0.000001"),
+            ("0.0000001", "1e-7"),
+            ("-1.5e-7", "-1.5e-7"),
+            ("4.50", "4.5"),
+            ("2e-3", "0.002"),
+            ("1.7976931348623157e308", "1.7976931348623157e+308"),
+            ("5e-324", "5e-324"),
+            ("333333333.33333329", "333333333.3333333"),
+        ] {
+            assert_eq!(number(input), expected, "{}", input);
+        }
+        assert!(write_number("1e400", &mut String::new()).is_err());
+    }
+
+    #[test]
+    fn test_precision_lost() {
+        let input = "[1.50, 0.1, 1E2, 9007199254740993, -0.0,\n 333333333.33333329]";
+        let item = transform(input, transform::parse(input).unwrap());
+        let (output, diagnostics) = canonicalize_item_with_diagnostics(&item).unwrap();
+        assert_eq!(output, "[1.5,0.1,100,9007199254740992,0,333333333.3333333]");
+        let messages: Vec<String> = diagnostics.iter().map(|d| d.to_string()).collect();
+        assert_eq!(
+            messages,
+            [
+                "1:18: warning[precision-lost]: number `9007199254740993` written as `9007199254740992`, the nearest double",
+                "2:2: warning[precision-lost]: number `333333333.33333329` written as `333333333.3333333`, the nearest double",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_canonical_document() {
+        let input = r#"{
+            // comments are dropped
+            "numbers": [333333333.33333329, 1E30, 4.50, 2e-3, 0.000000000000000000000000001],
+            "string": "\u20ac$\u000F\u000aA'\u0042\u0022\u005c\\\"\/",
+            "literals": [null, true, false]
+        }"#;
+        assert_eq!(
+            canonicalize(input).unwrap(),
+            r#"{"literals":[null,true,false],"numbers":[333333333.3333333,1e+30,4.5,0.002,1e-27],"string":"€$\u000f\nA'B\"\\\\\"/"}"#
+        );
+    }
+
+    #[test]
+    fn test_keys_sorted_by_utf16() {
+        // U+1F600 encodes as a surrogate pair (0xD83D...), which sorts before
+        // U+FB33 in UTF-16 but after it by code point
+        let input = "{\"\u{fb33}\": 1, \"\u{1f600}\": 2, \"\": 3, \"a\": 4}";
+        assert_eq!(
+            canonicalize(input).unwrap(),
+            "{\"\":3,\"a\":4,\"\u{1f600}\":2,\"\u{fb33}\":1}"
+        );
+        assert!(canonicalize(r#"{"a": 1, "a": 2}"#).is_err());
+    }
+}
@@ -1,4 +1,6 @@
+use crate::layout::{self, Layout};
 use crate::options::FracturedJsonOptions;
+use crate::parallel;
 use crate::types::{JsonItem, JsonItemType};
 
 // Character size constants for length calculations
@@ -14,28 +16,40 @@ pub struct ComputedItem {
     pub requires_multiple_lines: bool,
     pub name_length: usize,
     pub value_length: usize,
+    /// Layout chosen by the planning pass.
+    pub layout: Layout,
 }
 
 /// Reference to original item + its computed values and children
 #[derive(Debug, Clone)]
 pub struct ItemRef<'a> {
-    pub item: &'a JsonItem,
+    pub item: &'a JsonItem<'a>,
     pub computed: ComputedItem,
     pub children: Vec<ItemRef<'a>>,
 }
 
 impl<'a> ItemRef<'a> {
-    pub fn from_root(root: &'a JsonItem, options: &FracturedJsonOptions) -> Self {
-        Self::compute_recursive(root, options)
+    pub fn from_root(root: &'a JsonItem<'a>, options: &FracturedJsonOptions) -> Self {
+        Self::from_item(root, options, 0)
     }
 
-    fn compute_recursive(item: &'a JsonItem, options: &FracturedJsonOptions) -> Self {
+    /// Measures `item` and plans its layout for writing at `indent`.
+    pub fn from_item(
+        item: &'a JsonItem<'a>,
+        options: &FracturedJsonOptions,
+        indent: usize,
+    ) -> Self {
+        let mut computed = Self::compute_recursive(item, options);
+        layout::plan(&mut computed, options, indent);
+        computed
+    }
+
+    fn compute_recursive(item: &'a JsonItem<'a>, options: &FracturedJsonOptions) -> Self {
         // Compute children first
-        let children: Vec<_> = item
-            .children
-            .iter()
-            .map(|c| Self::compute_recursive(c, options))
-            .collect();
+        let mut children = parallel::map(&item.children, |c| Self::compute_recursive(c, options));
+        if item.item_type == JsonItemType::Object {
+            options.sort_keys.sort(&mut children, ItemRef::name);
+        }
 
         // Compute this node's values
         let complexity = compute_complexity_impl(item, &children);
@@ -48,6 +62,7 @@ impl<'a> ItemRef<'a> {
             requires_multiple_lines,
             name_length: item.name.len(),
             value_length: item.value.len(),
+            layout: Layout::Value,
         };
 
         Self {
@@ -113,6 +128,10 @@ impl<'a> ItemRef<'a> {
     pub fn requires_multiple_lines(&self) -> bool {
         self.computed.requires_multiple_lines
     }
+
+    pub fn layout(&self) -> &Layout {
+        &self.computed.layout
+    }
 }
 
 // Pure functions for computation (no mutation)
@@ -0,0 +1,607 @@
+//! Project configuration files.
+//!
+//! A config file is either `.fracturedjson.toml` or `.fracturedjson.jsonc`.
+//! Top-level keys are [`FracturedJsonOptions`] field names in kebab-case
+//! (`max-total-line-length = 100`). An `overrides` list applies extra settings
+//! to files matching glob patterns:
+//!
+//! ```toml
+//! indent-spaces = 2
+//!
+//! [[overrides]]
+//! files = ["package.json", "fixtures/**/*.json"]
+//! max-total-line-length = 80
+//! ```
+//!
+//! Patterns without a `/` match the file name anywhere below the config
+//! file's directory; patterns with a `/` match the path relative to it.
+
+use crate::error::{FracturedJsonError, Result};
+use crate::options::{FracturedJsonOptions, SortKeys};
+use globset::{GlobBuilder, GlobMatcher};
+use jsonc_parser::{JsonValue, ParseOptions};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// File names searched for, in order of preference within one directory.
+pub const CONFIG_FILE_NAMES: [&str; 2] = [".fracturedjson.toml", ".fracturedjson.jsonc"];
+
+/// A setting value as written in a config file.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigValue {
+    Bool(bool),
+    Integer(i64),
+    String(String),
+    Array(Vec<ConfigValue>),
+}
+
+/// Settings that apply to files matching any of `patterns`.
+#[derive(Debug, Clone)]
+pub struct ConfigOverride {
+    pub patterns: Vec<String>,
+    pub settings: Vec<(String, ConfigValue)>,
+    matchers: Vec<(GlobMatcher, bool)>,
+}
+
+/// A parsed configuration file.
+#[derive(Debug, Clone)]
+pub struct ConfigFile {
+    pub path: PathBuf,
+    pub settings: Vec<(String, ConfigValue)>,
+    pub overrides: Vec<ConfigOverride>,
+}
+
+impl ConfigFile {
+    /// Finds the nearest config file in `start_dir` or any of its ancestors.
+    pub fn discover(start_dir: &Path) -> Result<Option<Self>> {
+        for dir in start_dir.ancestors() {
+            for name in CONFIG_FILE_NAMES {
+                let candidate = dir.join(name);
+                if candidate.is_file() {
+                    return Self::load(&candidate).map(Some);
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Loads a config file, choosing the syntax from its extension.
+    pub fn load(path: &Path) -> Result<Self> {
+        // Overrides match paths relative to the config file's directory, and
+        // the files they are matched against are absolute
+        let path = &std::path::absolute(path)?;
+        let text = fs::read_to_string(path)?;
+        let is_toml = path.extension().is_some_and(|ext| ext == "toml");
+        let result = if is_toml {
+            Self::parse_toml(&text, path)
+        } else {
+            Self::parse_jsonc(&text, path)
+        };
+        result.map_err(|message| config_error(path, message))
+    }
+
+    fn parse_toml(text: &str, path: &Path) -> std::result::Result<Self, String> {
+        let table: toml::Table = text.parse().map_err(|e: toml::de::Error| e.to_string())?;
+        let mut config = ConfigFile {
+            path: path.to_path_buf(),
+            settings: Vec::new(),
+            overrides: Vec::new(),
+        };
+
+        for (key, value) in table {
+            if key == "overrides" {
+                let toml::Value::Array(entries) = value else {
+                    return Err("`overrides` must be an array of tables".into());
+                };
+                for entry in entries {
+                    let toml::Value::Table(entry) = entry else {
+                        return Err("`overrides` must be an array of tables".into());
+                    };
+                    let settings = entry
+                        .into_iter()
+                        .map(|(k, v)| Ok((k, from_toml(v)?)))
+                        .collect::<std::result::Result<_, String>>()?;
+                    config
+                        .overrides
+                        .push(ConfigOverride::from_settings(settings)?);
+                }
+            } else {
+                config.settings.push((key, from_toml(value)?));
+            }
+        }
+
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn parse_jsonc(text: &str, path: &Path) -> std::result::Result<Self, String> {
+        let value = jsonc_parser::parse_to_value(text, &ParseOptions::default())
+            .map_err(|e| e.to_string())?;
+        let Some(JsonValue::Object(object)) = value else {
+            return Err("expected a top-level object".into());
+        };
+        let mut config = ConfigFile {
+            path: path.to_path_buf(),
+            settings: Vec::new(),
+            overrides: Vec::new(),
+        };
+
+        for (key, value) in object {
+            if key == "overrides" {
+                let JsonValue::Array(entries) = value else {
+                    return Err("`overrides` must be an array of objects".into());
+                };
+                for entry in entries {
+                    let JsonValue::Object(entry) = entry else {
+                        return Err("`overrides` must be an array of objects".into());
+                    };
+                    let settings = entry
+                        .into_iter()
+                        .map(|(k, v)| Ok((k, from_json(v)?)))
+                        .collect::<std::result::Result<_, String>>()?;
+                    config
+                        .overrides
+                        .push(ConfigOverride::from_settings(settings)?);
+                }
+            } else {
+                config.settings.push((key, from_json(value)?));
+            }
+        }
+
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Rejects unknown keys and ill-typed values up front, so a typo is
+    /// reported even when no file matches the override it appears in.
+    fn validate(&self) -> std::result::Result<(), String> {
+        let mut scratch = FracturedJsonOptions::default();
+        let all_settings = self
+            .settings
+            .iter()
+            .chain(self.overrides.iter().flat_map(|o| o.settings.iter()));
+        for (key, value) in all_settings {
+            set_option(&mut scratch, key, value)?;
+        }
+        Ok(())
+    }
+
+    /// Applies the base settings, then every override matching `file`.
+    pub fn apply(&self, options: &mut FracturedJsonOptions, file: Option<&Path>) -> Result<()> {
+        for (key, value) in &self.settings {
+            set_option(options, key, value).map_err(|m| config_error(&self.path, m))?;
+        }
+
+        let Some(file) = file else {
+            return Ok(());
+        };
+        let base_dir = self.path.parent().unwrap_or(Path::new(""));
+        for config_override in &self.overrides {
+            if config_override.matches(file, base_dir) {
+                for (key, value) in &config_override.settings {
+                    set_option(options, key, value).map_err(|m| config_error(&self.path, m))?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ConfigOverride {
+    fn from_settings(
+        mut settings: Vec<(String, ConfigValue)>,
+    ) -> std::result::Result<Self, String> {
+        let Some(index) = settings.iter().position(|(k, _)| k == "files") else {
+            return Err("each override needs a `files` list".into());
+        };
+        let patterns = match settings.remove(index).1 {
+            ConfigValue::String(pattern) => vec![pattern],
+            ConfigValue::Array(values) => values
+                .into_iter()
+                .map(|v| match v {
+                    ConfigValue::String(pattern) => Ok(pattern),
+                    _ => Err("`files` entries must be strings".to_string()),
+                })
+                .collect::<std::result::Result<_, _>>()?,
+            _ => return Err("`files` must be a string or a list of strings".into()),
+        };
+
+        let matchers = patterns
+            .iter()
+            .map(|pattern| {
+                let glob = GlobBuilder::new(pattern)
+                    .literal_separator(true)
+                    .build()
+                    .map_err(|e| e.to_string())?;
+                Ok((glob.compile_matcher(), pattern.contains('/')))
+            })
+            .collect::<std::result::Result<_, String>>()?;
+
+        Ok(ConfigOverride {
+            patterns,
+            settings,
+            matchers,
+        })
+    }
+
+    /// Returns `true` if `file` matches one of the patterns, relative to the
+    /// directory containing the config file.
+    pub fn matches(&self, file: &Path, base_dir: &Path) -> bool {
+        let relative = file.strip_prefix(base_dir).unwrap_or(file);
+        self.matchers.iter().any(|(matcher, has_separator)| {
+            if *has_separator {
+                matcher.is_match(relative)
+            } else {
+                relative
+                    .file_name()
+                    .is_some_and(|name| matcher.is_match(name))
+            }
+        })
+    }
+}
+
+/// Sets the option named `key` (kebab-case; underscores are accepted).
+pub fn set_option(
+    options: &mut FracturedJsonOptions,
+    key: &str,
+    value: &ConfigValue,
+) -> std::result::Result<(), String> {
+    let key = key.replace('_', "-");
+    let invalid = |expected: &str| format!("`{}` expects {}, found {}", key, expected, value);
+
+    let as_usize = || match value {
+        ConfigValue::Integer(n) => {
+            usize::try_from(*n).map_err(|_| invalid("a non-negative integer"))
+        }
+        _ => Err(invalid("a non-negative integer")),
+    };
+    let as_u32 = || match value {
+        ConfigValue::Integer(n) => u32::try_from(*n).map_err(|_| invalid("a non-negative integer")),
+        _ => Err(invalid("a non-negative integer")),
+    };
+    let as_bool = || match value {
+        ConfigValue::Bool(b) => Ok(*b),
+        _ => Err(invalid("a boolean")),
+    };
+    let as_str = || match value {
+        ConfigValue::String(s) => Ok(s.as_str()),
+        _ => Err(invalid("a string")),
+    };
+
+    match key.as_str() {
+        "json-eol-style" => options.json_eol_style = as_str()?.parse().map_err(to_message)?,
+        "max-total-line-length" => options.max_total_line_length = as_usize()?,
+        "max-inline-complexity" => options.max_inline_complexity = as_u32()?,
+        "max-compact-array-complexity" => options.max_compact_array_complexity = as_u32()?,
+        "max-table-row-complexity" => options.max_table_row_complexity = as_u32()?,
+        "max-prop-name-padding" => options.max_prop_name_padding = as_usize()?,
+        "colon-before-prop-name-padding" => options.colon_before_prop_name_padding = as_bool()?,
+        "table-comma-placement" => {
+            options.table_comma_placement = as_str()?.parse().map_err(to_message)?
+        }
+        "min-compact-array-row-items" => options.min_compact_array_row_items = as_usize()?,
+        "sort-keys" => {
+            options.sort_keys = match value {
+                ConfigValue::String(name) => name.parse().map_err(to_message)?,
+                ConfigValue::Array(keys) => SortKeys::Custom(
+                    keys.iter()
+                        .map(|key| match key {
+                            ConfigValue::String(key) => Ok(key.clone()),
+                            _ => Err(invalid("a name or a list of keys")),
+                        })
+                        .collect::<std::result::Result<_, _>>()?,
+                ),
+                _ => return Err(invalid("a name or a list of keys")),
+            }
+        }
+        "duplicate-key-policy" => {
+            options.duplicate_key_policy = as_str()?.parse().map_err(to_message)?
+        }
+        "always-expand-depth" => match value {
+            ConfigValue::Integer(n) => {
+                options.always_expand_depth =
+                    i32::try_from(*n).map_err(|_| invalid("an integer"))?
+            }
+            _ => return Err(invalid("an integer")),
+        },
+        "nested-bracket-padding" => options.nested_bracket_padding = as_bool()?,
+        "simple-bracket-padding" => options.simple_bracket_padding = as_bool()?,
+        "colon-padding" => options.colon_padding = as_bool()?,
+        "comma-padding" => options.comma_padding = as_bool()?,
+        "comment-padding" => options.comment_padding = as_bool()?,
+        "number-list-alignment" => {
+            options.number_list_alignment = as_str()?.parse().map_err(to_message)?
+        }
+        "indent-spaces" => options.indent_spaces = as_usize()?,
+        "use-tab-to-indent" => options.use_tab_to_indent = as_bool()?,
+        "prefix-string" => options.prefix_string = as_str()?.to_string(),
+        "comment-policy" => options.comment_policy = as_str()?.parse().map_err(to_message)?,
+        "preserve-blank-lines" => options.preserve_blank_lines = as_bool()?,
+        "allow-trailing-commas" => options.allow_trailing_commas = as_bool()?,
+        "final-newline" => options.final_newline = as_bool()?,
+        _ => return Err(format!("unknown option `{}`", key)),
+    }
+    Ok(())
+}
+
+/// Lists every option with its current value, under its config file key.
+pub fn option_entries(options: &FracturedJsonOptions) -> Vec<(&'static str, ConfigValue)> {
+    use ConfigValue::{Bool, Integer, String};
+
+    let int = |n: usize| Integer(n as i64);
+    vec![
+        ("json-eol-style", String(options.json_eol_style.to_string())),
+        ("max-total-line-length", int(options.max_total_line_length)),
+        (
+            "max-inline-complexity",
+            Integer(options.max_inline_complexity.into()),
+        ),
+        (
+            "max-compact-array-complexity",
+            Integer(options.max_compact_array_complexity.into()),
+        ),
+        (
+            "max-table-row-complexity",
+            Integer(options.max_table_row_complexity.into()),
+        ),
+        ("max-prop-name-padding", int(options.max_prop_name_padding)),
+        (
+            "colon-before-prop-name-padding",
+            Bool(options.colon_before_prop_name_padding),
+        ),
+        (
+            "table-comma-placement",
+            String(options.table_comma_placement.to_string()),
+        ),
+        (
+            "min-compact-array-row-items",
+            int(options.min_compact_array_row_items),
+        ),
+        (
+            "sort-keys",
+            match &options.sort_keys {
+                SortKeys::Custom(keys) => {
+                    ConfigValue::Array(keys.iter().cloned().map(String).collect())
+                }
+                named => String(named.to_string()),
+            },
+        ),
+        (
+            "duplicate-key-policy",
+            String(options.duplicate_key_policy.to_string()),
+        ),
+        (
+            "always-expand-depth",
+            Integer(options.always_expand_depth.into()),
+        ),
+        (
+            "nested-bracket-padding",
+            Bool(options.nested_bracket_padding),
+        ),
+        (
+            "simple-bracket-padding",
+            Bool(options.simple_bracket_padding),
+        ),
+        ("colon-padding", Bool(options.colon_padding)),
+        ("comma-padding", Bool(options.comma_padding)),
+        ("comment-padding", Bool(options.comment_padding)),
+        (
+            "number-list-alignment",
+            String(options.number_list_alignment.to_string()),
+        ),
+        ("indent-spaces", int(options.indent_spaces)),
+        ("use-tab-to-indent", Bool(options.use_tab_to_indent)),
+        ("prefix-string", String(options.prefix_string.clone())),
+        ("comment-policy", String(options.comment_policy.to_string())),
+        ("preserve-blank-lines", Bool(options.preserve_blank_lines)),
+        ("allow-trailing-commas", Bool(options.allow_trailing_commas)),
+        ("final-newline", Bool(options.final_newline)),
+    ]
+}
+
+/// Renders `options` as a `.fracturedjson.toml` document.
+pub fn to_toml_string(options: &FracturedJsonOptions) -> String {
+    let mut out = String::new();
+    for (key, value) in option_entries(options) {
+        out.push_str(key);
+        out.push_str(" = ");
+        out.push_str(&value.to_string());
+        out.push('\n');
+    }
+    out
+}
+
+impl fmt::Display for ConfigValue {
+    /// Formats the value as a TOML literal.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigValue::Bool(b) => write!(f, "{}", b),
+            ConfigValue::Integer(n) => write!(f, "{}", n),
+            ConfigValue::String(s) => write!(f, "{}", toml::Value::String(s.clone())),
+            ConfigValue::Array(values) => {
+                f.write_str("[")?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}", value)?;
+                }
+                f.write_str("]")
+            }
+        }
+    }
+}
+
+fn from_toml(value: toml::Value) -> std::result::Result<ConfigValue, String> {
+    match value {
+        toml::Value::Boolean(b) => Ok(ConfigValue::Bool(b)),
+        toml::Value::Integer(n) => Ok(ConfigValue::Integer(n)),
+        toml::Value::String(s) => Ok(ConfigValue::String(s)),
+        toml::Value::Array(values) => values
+            .into_iter()
+            .map(from_toml)
+            .collect::<std::result::Result<_, _>>()
+            .map(ConfigValue::Array),
+        other => Err(format!("unsupported value `{}`", other)),
+    }
+}
+
+fn from_json(value: JsonValue) -> std::result::Result<ConfigValue, String> {
+    match value {
+        JsonValue::Boolean(b) => Ok(ConfigValue::Bool(b)),
+        JsonValue::Number(n) => n
+            .parse()
+            .map(ConfigValue::Integer)
+            .map_err(|_| format!("expected an integer, found `{}`", n)),
+        JsonValue::String(s) => Ok(ConfigValue::String(s.into_owned())),
+        JsonValue::Array(values) => values
+            .into_iter()
+            .map(from_json)
+            .collect::<std::result::Result<_, _>>()
+            .map(ConfigValue::Array),
+        JsonValue::Object(_) => Err("nested objects are only allowed in `overrides`".into()),
+        JsonValue::Null => Err("null is not a valid setting".into()),
+    }
+}
+
+fn to_message(err: FracturedJsonError) -> String {
+    match err {
+        FracturedJsonError::InvalidOption(message) => message,
+        other => other.to_string(),
+    }
+}
+
+fn config_error(path: &Path, message: String) -> FracturedJsonError {
+    FracturedJsonError::Config {
+        path: path.to_path_buf(),
+        message,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::NumberListAlignment;
+
+    #[test]
+    fn test_toml_settings_and_overrides() {
+        let text = r#"
+            indent-spaces = 2
+            number-list-alignment = "decimal"
+
+            [[overrides]]
+            files = ["*.lock.json", "fixtures/*.json"]
+            max-total-line-length = 80
+        "#;
+        let config = ConfigFile::parse_toml(text, Path::new("/repo/.fracturedjson.toml")).unwrap();
+
+        let mut options = FracturedJsonOptions::default();
+        config
+            .apply(&mut options, Some(Path::new("/repo/src/data.json")))
+            .unwrap();
+        assert_eq!(options.indent_spaces, 2);
+        assert_eq!(options.number_list_alignment, NumberListAlignment::Decimal);
+        assert_eq!(options.max_total_line_length, 120);
+
+        for matching in ["/repo/deep/dir/package.lock.json", "/repo/fixtures/a.json"] {
+            let mut options = FracturedJsonOptions::default();
+            config
+                .apply(&mut options, Some(Path::new(matching)))
+                .unwrap();
+            assert_eq!(options.max_total_line_length, 80, "{}", matching);
+        }
+
+        // `*` stays within one directory
+        let mut options = FracturedJsonOptions::default();
+        config
+            .apply(&mut options, Some(Path::new("/repo/fixtures/deep/b.json")))
+            .unwrap();
+        assert_eq!(options.max_total_line_length, 120);
+    }
+
+    #[test]
+    fn test_relative_config_path_matches_overrides() {
+        // Relative to the package root, where tests run
+        let dir = Path::new("target/config-tests/relative");
+        fs::create_dir_all(dir.join("fixtures")).unwrap();
+        let config_path = dir.join("cfg.toml");
+        fs::write(
+            &config_path,
+            "[[overrides]]\nfiles = [\"fixtures/*.json\"]\nmax-total-line-length = 10\n",
+        )
+        .unwrap();
+
+        let config = ConfigFile::load(&config_path).unwrap();
+        let file = std::path::absolute(dir.join("fixtures/x.json")).unwrap();
+        let mut options = FracturedJsonOptions::default();
+        config.apply(&mut options, Some(&file)).unwrap();
+        assert_eq!(options.max_total_line_length, 10);
+    }
+
+    #[test]
+    fn test_jsonc_config() {
+        let text = r#"{
+            // Comments are allowed
+            "max_total_line_length": 100,
+            "overrides": [{ "files": "*.json", "use-tab-to-indent": true }]
+        }"#;
+        let config =
+            ConfigFile::parse_jsonc(text, Path::new("/repo/.fracturedjson.jsonc")).unwrap();
+        let mut options = FracturedJsonOptions::default();
+        config
+            .apply(&mut options, Some(Path::new("/repo/a.json")))
+            .unwrap();
+        assert_eq!(options.max_total_line_length, 100);
+        assert!(options.use_tab_to_indent);
+    }
+
+    #[test]
+    fn test_invalid_settings_are_rejected() {
+        let path = Path::new(".fracturedjson.toml");
+        assert!(ConfigFile::parse_toml("max-line-lenght = 80", path).is_err());
+        assert!(ConfigFile::parse_toml("indent-spaces = -1", path).is_err());
+        assert!(ConfigFile::parse_toml("comment-policy = \"drop\"", path).is_err());
+        assert!(ConfigFile::parse_toml("sort-keys = [\"a\", 1]", path).is_err());
+        assert!(ConfigFile::parse_toml("[[overrides]]\nindent-spaces = 2", path).is_err());
+    }
+
+    #[test]
+    fn test_toml_round_trip() {
+        let options = FracturedJsonOptions {
+            prefix_string: "// \"x\"".to_string(),
+            always_expand_depth: 2,
+            sort_keys: SortKeys::Custom(vec!["name".into(), "version".into()]),
+            ..FracturedJsonOptions::default()
+        };
+        let text = to_toml_string(&options);
+        let config = ConfigFile::parse_toml(&text, Path::new(".fracturedjson.toml")).unwrap();
+        let mut parsed = FracturedJsonOptions::default();
+        config.apply(&mut parsed, None).unwrap();
+        assert_eq!(to_toml_string(&parsed), text);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_names_match_config_keys() {
+        let options = FracturedJsonOptions {
+            number_list_alignment: NumberListAlignment::Decimal,
+            indent_spaces: 2,
+            sort_keys: SortKeys::Custom(vec!["id".into()]),
+            ..FracturedJsonOptions::default()
+        };
+        let serialized = toml::to_string(&options).unwrap();
+        let parsed: toml::Table = serialized.parse().unwrap();
+        let keys: Vec<&str> = option_entries(&options).iter().map(|(k, _)| *k).collect();
+        assert_eq!(parsed.len(), keys.len());
+        for (key, value) in option_entries(&options) {
+            assert_eq!(parsed[key].to_string(), value.to_string(), "{}", key);
+        }
+
+        let partial: FracturedJsonOptions = toml::from_str("indent-spaces = 2").unwrap();
+        assert_eq!(partial.indent_spaces, 2);
+        assert_eq!(partial.max_total_line_length, 120);
+        let natural: FracturedJsonOptions = toml::from_str("sort-keys = \"natural\"").unwrap();
+        assert_eq!(natural.sort_keys, SortKeys::Natural);
+    }
+}
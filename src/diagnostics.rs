@@ -0,0 +1,209 @@
+//! Problems found in an input that don't stop it from being formatted.
+
+use crate::computed::ItemRef;
+use crate::formatter::{escaped_len, simple_value_len};
+use crate::layout::{self, Layout};
+use crate::options::FracturedJsonOptions;
+use crate::types::{InputPosition, JsonItemType};
+use std::fmt;
+
+/// How serious a [`Diagnostic`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+/// What a [`Diagnostic`] is about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagnosticCode {
+    /// A property repeats the name of an earlier one in the same object.
+    DuplicateKey,
+    /// A value too long for `max_total_line_length` even on a line of its own.
+    LineTooLong,
+    /// A comment in a place the formatter can't keep one.
+    CommentDropped,
+    /// A comment written somewhere other than where it was, such as a
+    /// trailing comment moved in front of the next item.
+    CommentMoved,
+    /// A number written with a different value than the source had.
+    PrecisionLost,
+}
+
+impl DiagnosticCode {
+    /// Kebab-case name, as printed in diagnostics.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DiagnosticCode::DuplicateKey => "duplicate-key",
+            DiagnosticCode::LineTooLong => "line-too-long",
+            DiagnosticCode::CommentDropped => "comment-dropped",
+            DiagnosticCode::CommentMoved => "comment-moved",
+            DiagnosticCode::PrecisionLost => "precision-lost",
+        }
+    }
+}
+
+/// A problem found in the input, or in the output made from it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: DiagnosticCode,
+    pub message: String,
+    /// Where in the input the problem is; `None` for items that weren't
+    /// parsed from text, such as those built from serde values.
+    pub position: Option<InputPosition>,
+}
+
+impl Diagnostic {
+    pub(crate) fn warning(
+        code: DiagnosticCode,
+        position: InputPosition,
+        message: impl Into<String>,
+    ) -> Self {
+        Diagnostic {
+            severity: Severity::Warning,
+            code,
+            message: message.into(),
+            // Parsed items always start at line 1 or later
+            position: (position.line > 0).then_some(position),
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        })
+    }
+}
+
+impl fmt::Display for DiagnosticCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    /// Writes `line:column: severity[code]: message`, leaving out the
+    /// position when there is none.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(position) = self.position {
+            write!(f, "{}:{}: ", position.line, position.column)?;
+        }
+        write!(f, "{}[{}]: {}", self.severity, self.code, self.message)
+    }
+}
+
+/// Reports the lines of a planned tree that are longer than
+/// `max_total_line_length`. Only values and inline containers can overflow:
+/// every other layout was chosen because it fits.
+pub fn line_length_diagnostics(
+    computed: &ItemRef,
+    options: &FracturedJsonOptions,
+) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    check_line_length(computed, options, 0, false, &mut diagnostics);
+    diagnostics
+}
+
+fn check_line_length(
+    item: &ItemRef,
+    options: &FracturedJsonOptions,
+    indent: usize,
+    is_property: bool,
+    out: &mut Vec<Diagnostic>,
+) {
+    if item.item_type() == JsonItemType::BlankLine {
+        return;
+    }
+    let is_object = item.item_type() == JsonItemType::Object;
+    match item.layout() {
+        Layout::Expanded { .. } | Layout::AlignedObject { .. } => {
+            let child_indent = layout::child_indent(item.layout(), indent);
+            for child in &item.children {
+                check_line_length(child, options, child_indent, is_object, out);
+            }
+        }
+        // Rows are measured to fit, but a single element can still be too
+        // long for a row of its own
+        Layout::CompactArray { .. } | Layout::Table { .. } => {
+            for child in &item.children {
+                report_overflow(child, options, indent + 1, is_object, out);
+            }
+        }
+        Layout::Value | Layout::Inline => report_overflow(item, options, indent, is_property, out),
+    }
+}
+
+/// Reports `item` if it doesn't fit on one line at `indent`.
+fn report_overflow(
+    item: &ItemRef,
+    options: &FracturedJsonOptions,
+    indent: usize,
+    is_property: bool,
+    out: &mut Vec<Diagnostic>,
+) {
+    let name_length = if is_property {
+        // Quotes and colon, and the space after it
+        escaped_len(item.name()) + 3 + usize::from(options.colon_padding)
+    } else {
+        0
+    };
+    let value_length = match item.layout() {
+        Layout::Value => simple_value_len(item),
+        _ => item.minimum_total_length(),
+    };
+    let length = indent * options.indent_spaces + name_length + value_length;
+    if length > options.max_total_line_length {
+        out.push(Diagnostic::warning(
+            DiagnosticCode::LineTooLong,
+            item.item.input_position,
+            format!(
+                "line is {} characters, over max_total_line_length {}",
+                length, options.max_total_line_length
+            ),
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transform::{parse, transform};
+
+    fn overflows(input: &str, max_total_line_length: usize) -> Vec<String> {
+        let options = FracturedJsonOptions {
+            max_total_line_length,
+            ..FracturedJsonOptions::default()
+        };
+        let item = transform(input, parse(input).unwrap());
+        let computed = ItemRef::from_root(&item, &options);
+        line_length_diagnostics(&computed, &options)
+            .iter()
+            .map(|d| d.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_line_too_long() {
+        let input = "{\n  \"short\": 1,\n  \"long\": \"abcdefghijklmnopqrstuvwxyz\",\n  \"list\": [1, 2, 3]\n}";
+        assert_eq!(
+            overflows(input, 30),
+            ["3:3: warning[line-too-long]: line is 40 characters, over max_total_line_length 30"]
+        );
+        assert!(overflows(input, 40).is_empty());
+        assert_eq!(overflows(input, 17).len(), 2);
+
+        // No formatter writes prefix_string, so it doesn't count
+        let options = FracturedJsonOptions {
+            max_total_line_length: 40,
+            prefix_string: "// ".repeat(10),
+            ..FracturedJsonOptions::default()
+        };
+        let item = transform(input, parse(input).unwrap());
+        let computed = ItemRef::from_root(&item, &options);
+        assert!(line_length_diagnostics(&computed, &options).is_empty());
+    }
+}
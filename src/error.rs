@@ -10,9 +10,32 @@ pub enum FracturedJsonError {
     #[error("Invalid option: {0}")]
     InvalidOption(String),
 
+    #[error("Config error in {}: {message}", path.display())]
+    Config {
+        path: std::path::PathBuf,
+        message: String,
+    },
+
+    #[error("Syntax error at line {line}, column {column}: {message}")]
+    Syntax {
+        line: usize,
+        column: usize,
+        message: String,
+    },
+
+    #[error("Duplicate key `{key}` at line {line}, column {column}")]
+    DuplicateKey {
+        key: String,
+        line: usize,
+        column: usize,
+    },
+
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 
+    #[error("Serialization error: {0}")]
+    Serialize(String),
+
     #[error("Formatting error: {0}")]
     FormattingError(String),
 }
@@ -0,0 +1,238 @@
+//! Explains the layout planned for each container, and why the layouts
+//! tried before it were passed over.
+
+use crate::computed::ItemRef;
+use crate::error::Result;
+use crate::layout::{self, Layout};
+use crate::options::FracturedJsonOptions;
+use crate::transform;
+use crate::types::{JsonItem, JsonItemType};
+use std::fmt;
+
+/// The layout planned for one container and how it was arrived at.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Explanation {
+    /// JSON Pointer (RFC 6901) to the container; empty for the root.
+    pub pointer: String,
+    /// Layout the container is written with.
+    pub layout: Layout,
+    /// Indent level the container was planned at.
+    pub indent: usize,
+    /// Nesting depth, compared against the `max_*_complexity` options.
+    pub complexity: u32,
+    /// Length of the container on one line, indent included.
+    pub length: usize,
+    /// Layouts tried before `layout`, in order, with the reason each failed.
+    pub rejected: Vec<RejectedLayout>,
+}
+
+/// A layout that was tried for a container and ruled out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RejectedLayout {
+    /// Name of the layout, as returned by [`Layout::name`].
+    pub layout: &'static str,
+    pub reason: String,
+}
+
+impl fmt::Display for RejectedLayout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.layout, self.reason)
+    }
+}
+
+impl fmt::Display for Explanation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pointer = if self.pointer.is_empty() {
+            "(root)"
+        } else {
+            &self.pointer
+        };
+        write!(f, "{pointer}: {}", self.layout.name())?;
+        match &self.layout {
+            Layout::CompactArray { items_per_row, .. } => write!(f, ", {items_per_row} per row")?,
+            Layout::Table { column_widths, .. } => write!(f, ", {} columns", column_widths.len())?,
+            Layout::AlignedObject { name_padding } => {
+                write!(f, ", names padded to {name_padding}")?
+            }
+            Layout::Expanded {
+                numbers: Some(numbers),
+            } => write!(f, ", numbers padded to {}", numbers.width)?,
+            _ => {}
+        }
+        write!(
+            f,
+            " (complexity {}, length {} at indent {})",
+            self.complexity, self.length, self.indent
+        )?;
+        for rejected in &self.rejected {
+            write!(f, "\n    not {rejected}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses `input` and explains the layout of every container in it, in
+/// document order.
+pub fn explain(input: &str, options: &FracturedJsonOptions) -> Result<Vec<Explanation>> {
+    let (item, _) = transform::parse_document(input, options)?;
+    Ok(explain_item(&item, options))
+}
+
+/// Explains the layout of every container in `item`, in document order.
+pub fn explain_item(item: &JsonItem, options: &FracturedJsonOptions) -> Vec<Explanation> {
+    let computed = ItemRef::from_root(item, options);
+    let mut explanations = Vec::new();
+    let mut pointer = String::new();
+    explain_recursive(&computed, options, 0, true, &mut pointer, &mut explanations);
+    explanations
+}
+
+fn explain_recursive(
+    item: &ItemRef,
+    options: &FracturedJsonOptions,
+    indent: usize,
+    chosen: bool,
+    pointer: &mut String,
+    out: &mut Vec<Explanation>,
+) {
+    let is_object = match item.item_type() {
+        JsonItemType::Object => true,
+        JsonItemType::Array => false,
+        _ => return,
+    };
+
+    // Replay the decision that planning made, this time keeping the reasons.
+    // Items their parent writes on one line had no choice to make.
+    let mut rejected = Vec::new();
+    if chosen {
+        layout::choose_layout(item, options, indent, &mut |layout, reason| {
+            rejected.push(RejectedLayout {
+                layout,
+                reason: reason.to_string(),
+            })
+        });
+    }
+
+    out.push(Explanation {
+        pointer: pointer.clone(),
+        layout: item.layout().clone(),
+        indent,
+        complexity: item.complexity(),
+        length: item.minimum_total_length() + indent * options.indent_spaces,
+        rejected,
+    });
+
+    let child_indent = layout::child_indent(item.layout(), indent);
+    let children_chosen = !layout::writes_children_inline(item.layout());
+    // Comments are never containers, and only follow the last element, so
+    // they don't shift the indices of the values
+    for (index, child) in item.children.iter().enumerate() {
+        let len = pointer.len();
+        pointer.push('/');
+        if is_object {
+            push_escaped_token(pointer, child.name());
+        } else {
+            pointer.push_str(&index.to_string());
+        }
+        explain_recursive(child, options, child_indent, children_chosen, pointer, out);
+        pointer.truncate(len);
+    }
+}
+
+/// Appends a reference token, escaping `~` and `/` per RFC 6901.
+fn push_escaped_token(pointer: &mut String, token: &str) {
+    for ch in token.chars() {
+        match ch {
+            '~' => pointer.push_str("~0"),
+            '/' => pointer.push_str("~1"),
+            _ => pointer.push(ch),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pointers_and_layouts() {
+        let input = r#"{
+            "a/b": [1, 2],
+            "m~n": {"deep": [[], {} // not a value
+            ]}
+        }"#;
+        let explanations = explain(input, &FracturedJsonOptions::default()).unwrap();
+        let pointers: Vec<&str> = explanations.iter().map(|e| e.pointer.as_str()).collect();
+        assert_eq!(
+            pointers,
+            [
+                "",
+                "/a~1b",
+                "/m~0n",
+                "/m~0n/deep",
+                "/m~0n/deep/0",
+                "/m~0n/deep/1"
+            ]
+        );
+        assert_eq!(explanations[0].layout.name(), "expanded");
+        assert_eq!(explanations[0].rejected[0].layout, "inline");
+        assert_eq!(explanations[1].layout, Layout::Inline);
+        assert!(explanations[1].rejected.is_empty());
+    }
+
+    #[test]
+    fn test_reports_deciding_factors() {
+        let options = FracturedJsonOptions {
+            max_total_line_length: 40,
+            max_inline_complexity: 2,
+            max_table_row_complexity: 2,
+            ..FracturedJsonOptions::default()
+        };
+        let input = r#"{"first": {"x": 1}, "second": {"x": 2, "y": 3}, "third": 4}"#;
+        let root = &explain(input, &options).unwrap()[0];
+        let reasons: Vec<String> = root.rejected.iter().map(|r| r.to_string()).collect();
+        assert_eq!(
+            reasons,
+            [
+                "inline: length 59 > max_total_line_length 40",
+                "table: property \"second\" has different keys from \"first\"",
+                "aligned-object: property \"second\" has different keys from \"first\"",
+            ]
+        );
+
+        let deep = r#"[[[[1]]], [2]]"#;
+        let root = &explain(deep, &FracturedJsonOptions::default()).unwrap()[0];
+        assert_eq!(
+            root.rejected[0].reason,
+            "complexity 4 > max_inline_complexity 1"
+        );
+        assert!(root
+            .to_string()
+            .starts_with("(root): expanded (complexity 4"));
+    }
+
+    #[test]
+    fn test_compact_rows_match_output() {
+        let numbers: Vec<String> = (0..38).map(|n| n.to_string()).collect();
+        // Inline is ruled out by complexity, so the whole array would fit on
+        // one row and the plan is capped at the element count
+        let nested = "[[1, 2], [3, 4], [5, 6], [7, 8]]";
+        for (input, len) in [
+            (format!("[{}]", numbers.join(", ")), 38),
+            (nested.into(), 4),
+        ] {
+            let options = FracturedJsonOptions::default();
+            let root = &explain(&input, &options).unwrap()[0];
+            let Layout::CompactArray { items_per_row, .. } = root.layout else {
+                panic!("{input} is not a compact array: {root}");
+            };
+            assert!(items_per_row <= len, "{root}");
+            let output = crate::format_jsonc(&input, &options).unwrap();
+            let rows = output.lines().count() - 2;
+            assert_eq!(rows, len.div_ceil(items_per_row), "{root}\n{output}");
+            assert!(root
+                .to_string()
+                .contains(&format!(", {items_per_row} per row")));
+        }
+    }
+}
@@ -1,275 +1,277 @@
 use crate::computed::ItemRef;
-use crate::options::{FracturedJsonOptions, NumberListAlignment, TableCommaPlacement};
+use crate::layout::{self, Layout};
+use crate::numbers::NumberColumn;
+use crate::options::{FracturedJsonOptions, TableCommaPlacement};
+#[cfg(feature = "rayon")]
+use crate::parallel;
 use crate::types::{JsonItem, JsonItemType};
 use std::borrow::Cow;
+use std::fmt;
+use std::io;
 
 pub fn format(item: &JsonItem, options: &FracturedJsonOptions) -> String {
     // Build computed tree with references (no clone!)
     let computed = ItemRef::from_root(item, options);
+    format_computed(&computed, options)
+}
 
+/// Formats an already computed tree, skipping the measuring pass.
+pub fn format_computed(computed: &ItemRef, options: &FracturedJsonOptions) -> String {
     // Estimate capacity: minimum length + 20% for indentation/line breaks + base buffer
     let estimated_capacity =
         computed.minimum_total_length() + computed.minimum_total_length() / 5 + 100;
-    let mut buffer = String::with_capacity(estimated_capacity);
-    format_item(&computed, options, 0, &mut buffer);
+    let mut buffer = Output::new(String::with_capacity(estimated_capacity), None);
+    format_root(computed, options, &mut buffer);
 
-    buffer
+    buffer.buf
 }
 
-fn format_item(item: &ItemRef, options: &FracturedJsonOptions, indent: usize, buffer: &mut String) {
-    match item.item_type() {
-        JsonItemType::Array => {
-            if should_inline(item, options, indent) {
-                format_inline_array(item, options, indent, buffer);
-            } else if should_format_as_compact_array(item, options, indent) {
-                format_compact_array(item, options, indent, buffer);
-            } else if should_format_as_table(item, options, indent) {
-                format_table_array(item, options, indent, buffer);
-            } else {
-                format_expanded_array(item, options, indent, buffer);
-            }
-        }
-        JsonItemType::Object => {
-            if should_inline(item, options, indent) {
-                format_inline_object(item, options, indent, buffer);
-            } else if should_format_as_table(item, options, indent) {
-                format_table_object(item, options, indent, buffer);
-            } else {
-                format_expanded_object(item, options, indent, buffer);
-            }
-        }
-        JsonItemType::String => {
-            write_indent(options, indent, buffer);
-            write_quotes(item.value(), buffer);
-        }
-        JsonItemType::Number => {
-            write_indent(options, indent, buffer);
-            buffer.push_str(item.value());
-        }
-        JsonItemType::True => {
-            write_indent(options, indent, buffer);
-            buffer.push_str("true");
-        }
-        JsonItemType::False => {
-            write_indent(options, indent, buffer);
-            buffer.push_str("false");
-        }
-        JsonItemType::Null => {
-            write_indent(options, indent, buffer);
-            buffer.push_str("null");
-        }
-        JsonItemType::LineComment => {
-            if options.comment_policy == crate::options::CommentPolicy::Preserve {
-                write_indent(options, indent, buffer);
-                buffer.push_str(item.value());
-            }
-        }
-        JsonItemType::BlockComment => {
-            if options.comment_policy == crate::options::CommentPolicy::Preserve {
-                write_indent(options, indent, buffer);
-                buffer.push_str(item.value());
-            }
-        }
-        JsonItemType::BlankLine => {
-            buffer.push_str(options.eol_string());
-        }
-    }
+/// Formats `item` into an `io::Write`, handing output over in chunks instead
+/// of building the whole document in memory.
+pub fn format_to_writer<W: io::Write + ?Sized>(
+    item: &JsonItem,
+    options: &FracturedJsonOptions,
+    writer: &mut W,
+) -> io::Result<()> {
+    format_computed_to_writer(&ItemRef::from_root(item, options), options, writer)
 }
 
-fn should_inline(item: &ItemRef, options: &FracturedJsonOptions, indent: usize) -> bool {
-    if item.requires_multiple_lines() {
-        return false;
-    }
-
-    if item.has_comments() {
-        return false;
-    }
-
-    if item.complexity() > options.max_inline_complexity {
-        return false;
-    }
-
-    if item.minimum_total_length() + indent * options.indent_spaces > options.max_total_line_length
-    {
-        return false;
+/// Formats an already computed tree into an `io::Write`. See
+/// [`format_to_writer`].
+pub fn format_computed_to_writer<W: io::Write + ?Sized>(
+    computed: &ItemRef,
+    options: &FracturedJsonOptions,
+    writer: &mut W,
+) -> io::Result<()> {
+    let mut adapter = IoAdapter {
+        inner: writer,
+        error: None,
+    };
+    match format_computed_to_fmt_writer(computed, options, &mut adapter) {
+        Ok(()) => Ok(()),
+        Err(fmt::Error) => Err(adapter
+            .error
+            .unwrap_or_else(|| io::Error::other("formatter error"))),
     }
+}
 
-    true
+/// Formats `item` into a `fmt::Write`, handing output over in chunks.
+pub fn format_to_fmt_writer<W: fmt::Write + ?Sized>(
+    item: &JsonItem,
+    options: &FracturedJsonOptions,
+    writer: &mut W,
+) -> fmt::Result {
+    format_computed_to_fmt_writer(&ItemRef::from_root(item, options), options, writer)
 }
 
-fn should_format_as_compact_array(
-    item: &ItemRef,
+fn format_computed_to_fmt_writer<W: fmt::Write + ?Sized>(
+    computed: &ItemRef,
     options: &FracturedJsonOptions,
-    _indent: usize,
-) -> bool {
-    if item.item_type() != JsonItemType::Array {
-        return false;
-    }
+    writer: &mut W,
+) -> fmt::Result {
+    let mut sink = |chunk: &str| writer.write_str(chunk);
+    let mut buffer = Output::new(String::with_capacity(FLUSH_THRESHOLD * 2), Some(&mut sink));
+    format_root(computed, options, &mut buffer);
+    buffer.finish()
+}
 
-    if item.is_empty() {
-        return false;
-    }
+/// Formats `item` as a value nested `indent` levels deep, appending to `out`.
+pub(crate) fn format_nested(
+    item: &JsonItem,
+    options: &FracturedJsonOptions,
+    indent: usize,
+    out: &mut String,
+) {
+    let computed = ItemRef::from_item(item, options, indent);
+    let mut buffer = Output::new(std::mem::take(out), None);
+    format_item(&computed, options, indent, &mut buffer);
+    *out = buffer.buf;
+}
 
-    if item.complexity() > options.max_compact_array_complexity {
-        return false;
-    }
+fn format_root(computed: &ItemRef, options: &FracturedJsonOptions, buffer: &mut Output<'_>) {
+    format_item(computed, options, 0, buffer);
 
-    if item.has_comments() {
-        return false;
+    if options.final_newline {
+        buffer.push_str(options.eol_string());
     }
+}
 
-    if item.children.len() < options.min_compact_array_row_items {
-        return false;
+/// Formats each child of a wide top-level container into its own buffer,
+/// concurrently, for the caller to join in order. Returns `None` when the
+/// children should be formatted in place.
+#[cfg(feature = "rayon")]
+fn render_children_concurrently(
+    item: &ItemRef,
+    options: &FracturedJsonOptions,
+    indent: usize,
+) -> Option<Vec<String>> {
+    if indent != 0 || item.children.len() < parallel::PARALLEL_THRESHOLD {
+        return None;
     }
-
-    true
+    Some(parallel::map(&item.children, |child| {
+        let mut out = Output::new(String::new(), None);
+        format_item(child, options, indent + 1, &mut out);
+        out.buf
+    }))
 }
 
-fn should_format_as_table(item: &ItemRef, options: &FracturedJsonOptions, _indent: usize) -> bool {
-    if item.item_type() == JsonItemType::Array {
-        // Don't use table format if comments are present
-        if item.requires_multiple_lines() {
-            return false;
-        }
+#[cfg(not(feature = "rayon"))]
+fn render_children_concurrently(
+    _item: &ItemRef,
+    _options: &FracturedJsonOptions,
+    _indent: usize,
+) -> Option<Vec<String>> {
+    None
+}
 
-        if item.is_empty() {
-            return false;
-        }
+/// Buffered output is passed to the sink once it grows past this many bytes.
+const FLUSH_THRESHOLD: usize = 8 * 1024;
 
-        if item.complexity() > options.max_table_row_complexity {
-            return false;
-        }
+/// Destination for formatted text: a `String`, optionally drained into a sink
+/// whenever it grows past [`FLUSH_THRESHOLD`].
+struct Output<'w> {
+    buf: String,
+    sink: Option<&'w mut dyn FnMut(&str) -> fmt::Result>,
+    result: fmt::Result,
+}
 
-        if !can_format_as_table_array(item, options) {
-            return false;
+impl<'w> Output<'w> {
+    fn new(buf: String, sink: Option<&'w mut dyn FnMut(&str) -> fmt::Result>) -> Self {
+        Output {
+            buf,
+            sink,
+            result: Ok(()),
         }
-
-        return true;
     }
 
-    if item.item_type() == JsonItemType::Object {
-        if item.is_empty() {
-            return false;
-        }
-
-        if item.complexity() > options.max_table_row_complexity {
-            return false;
-        }
+    #[inline]
+    fn push(&mut self, c: char) {
+        self.buf.push(c);
+    }
 
-        if !can_format_as_table_object(item, options) {
-            return false;
+    #[inline]
+    fn push_str(&mut self, s: &str) {
+        self.buf.push_str(s);
+        if self.buf.len() >= FLUSH_THRESHOLD {
+            self.flush();
         }
-
-        return true;
     }
 
-    false
-}
-
-fn can_format_as_table_array(item: &ItemRef, _options: &FracturedJsonOptions) -> bool {
-    if item.is_empty() {
-        return false;
+    fn flush(&mut self) {
+        if let Some(sink) = self.sink.as_mut() {
+            if self.result.is_ok() {
+                self.result = sink(&self.buf);
+            }
+            self.buf.clear();
+        }
     }
 
-    let first_type = get_item_type_for_table(&item.children[0]);
-    if first_type.is_none() {
-        return false;
+    /// Drains what is left and reports the first sink error, if any.
+    fn finish(mut self) -> fmt::Result {
+        self.flush();
+        self.result
     }
-
-    item.children
-        .iter()
-        .all(|c| get_item_type_for_table(c) == first_type)
 }
 
-fn can_format_as_table_object(item: &ItemRef, _options: &FracturedJsonOptions) -> bool {
-    if item.is_empty() {
-        return false;
-    }
+/// Lets `io::Write` targets be driven through `fmt::Write`, keeping the
+/// underlying I/O error.
+struct IoAdapter<'a, W: io::Write + ?Sized> {
+    inner: &'a mut W,
+    error: Option<io::Error>,
+}
 
-    let first_props = get_property_names(&item.children[0]);
-    if first_props.is_empty() {
-        return false;
+impl<W: io::Write + ?Sized> fmt::Write for IoAdapter<'_, W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.inner.write_all(s.as_bytes()).map_err(|e| {
+            self.error = Some(e);
+            fmt::Error
+        })
     }
-
-    item.children
-        .iter()
-        .all(|c| get_property_names(c).as_slice() == first_props.as_slice())
 }
 
-fn get_item_type_for_table(item: &ItemRef) -> Option<JsonItemType> {
+/// Writes `item` nested `indent` levels deep. The caller has already
+/// positioned its first line; later lines are indented here.
+fn format_item(
+    item: &ItemRef,
+    options: &FracturedJsonOptions,
+    indent: usize,
+    buffer: &mut Output<'_>,
+) {
     match item.item_type() {
-        JsonItemType::Object => {
-            if item.is_empty() {
-                None
-            } else {
-                Some(JsonItemType::Object)
+        JsonItemType::Array | JsonItemType::Object => {
+            let is_array = item.item_type() == JsonItemType::Array;
+            match item.layout() {
+                Layout::Inline if is_array => format_inline_array(item, options, buffer),
+                Layout::Inline => format_inline_object(item, options, indent, buffer),
+                Layout::CompactArray {
+                    items_per_row,
+                    column_widths,
+                    number_columns,
+                } => format_compact_array(
+                    item,
+                    options,
+                    indent,
+                    *items_per_row,
+                    column_widths,
+                    number_columns,
+                    buffer,
+                ),
+                Layout::Table {
+                    name_padding,
+                    column_widths,
+                    number_columns,
+                } => format_table(
+                    item,
+                    options,
+                    indent,
+                    *name_padding,
+                    column_widths,
+                    number_columns,
+                    buffer,
+                ),
+                Layout::AlignedObject { name_padding } => {
+                    format_aligned_object(item, options, indent, *name_padding, buffer)
+                }
+                Layout::Expanded { numbers } if is_array => {
+                    format_expanded_array(item, options, indent, numbers.as_ref(), buffer)
+                }
+                Layout::Expanded { .. } | Layout::Value => {
+                    format_expanded_object(item, options, indent, buffer)
+                }
             }
         }
-        JsonItemType::Array => {
-            if item.is_empty() {
-                None
-            } else {
-                Some(JsonItemType::Array)
-            }
+        JsonItemType::String => {
+            write_quotes(item.value(), buffer);
         }
-        JsonItemType::String
-        | JsonItemType::Number
-        | JsonItemType::True
-        | JsonItemType::False
-        | JsonItemType::Null => Some(item.item_type()),
-        _ => None,
-    }
-}
-
-fn get_property_names<'a>(item: &'a ItemRef<'a>) -> Vec<&'a str> {
-    if item.item_type() != JsonItemType::Object {
-        return Vec::new();
-    }
-    item.children.iter().map(|c| c.name()).collect()
-}
-
-fn format_number_aligned(
-    value: &str,
-    options: &FracturedJsonOptions,
-    other_values: &[&str],
-) -> String {
-    match options.number_list_alignment {
-        NumberListAlignment::None => value.to_string(),
-        NumberListAlignment::Left => {
-            if let Some(max_len) = other_values.iter().map(|v| v.len()).max() {
-                format!("{:<width$}", value, width = max_len)
-            } else {
-                value.to_string()
+        JsonItemType::Number => {
+            buffer.push_str(item.value());
+        }
+        JsonItemType::True => {
+            buffer.push_str("true");
+        }
+        JsonItemType::False => {
+            buffer.push_str("false");
+        }
+        JsonItemType::Null => {
+            buffer.push_str("null");
+        }
+        JsonItemType::LineComment => {
+            if options.comment_policy == crate::options::CommentPolicy::Preserve {
+                buffer.push_str(item.value());
             }
         }
-        NumberListAlignment::Decimal => {
-            let max_integer_part = other_values
-                .iter()
-                .filter_map(|v| v.split('.').next())
-                .map(|s| s.len())
-                .max()
-                .unwrap_or(value.len());
-
-            if value.contains('.') {
-                let parts: Vec<&str> = value.split('.').collect();
-                if parts.len() == 2 {
-                    format!(
-                        "{: >width$}.{}",
-                        parts[0],
-                        parts[1],
-                        width = max_integer_part
-                    )
-                } else {
-                    value.to_string()
-                }
-            } else {
-                format!("{: >width$}", value, width = max_integer_part)
+        JsonItemType::BlockComment => {
+            if options.comment_policy == crate::options::CommentPolicy::Preserve {
+                buffer.push_str(item.value());
             }
         }
+        JsonItemType::BlankLine => {
+            buffer.push_str(options.eol_string());
+        }
     }
 }
 
-fn write_indent(options: &FracturedJsonOptions, indent: usize, buffer: &mut String) {
+fn write_indent(options: &FracturedJsonOptions, indent: usize, buffer: &mut Output<'_>) {
     if options.use_tab_to_indent {
         for _ in 0..indent {
             buffer.push('\t');
@@ -281,7 +283,7 @@ fn write_indent(options: &FracturedJsonOptions, indent: usize, buffer: &mut Stri
 
 /// Efficiently writes spaces to buffer without allocations.
 /// Uses a pre-allocated string slice and slices it as needed.
-fn write_spaces(buffer: &mut String, count: usize) {
+fn write_spaces(buffer: &mut Output<'_>, count: usize) {
     const SPACES: &str = "                                "; // 32 spaces
     let mut remaining = count;
 
@@ -295,19 +297,19 @@ fn write_spaces(buffer: &mut String, count: usize) {
     }
 }
 
-fn write_quotes(s: &str, buffer: &mut String) {
+fn write_quotes(s: &str, buffer: &mut Output<'_>) {
     buffer.push('"');
     buffer.push_str(&escape_string(s));
     buffer.push('"');
 }
 
-fn write_quoted_property_name(s: &str, buffer: &mut String) {
+fn write_quoted_property_name(s: &str, buffer: &mut Output<'_>) {
     buffer.push('"');
-    buffer.push_str(s);
+    buffer.push_str(&escape_string(s));
     buffer.push('"');
 }
 
-fn escape_string(s: &str) -> Cow<'_, str> {
+pub(crate) fn escape_string(s: &str) -> Cow<'_, str> {
     // Check if any character needs escaping - enable zero-copy for common case
     let needs_escape = s
         .chars()
@@ -356,17 +358,24 @@ fn escape_string(s: &str) -> Cow<'_, str> {
     Cow::Owned(result)
 }
 
-fn write_prefix_comment(item: &ItemRef, options: &FracturedJsonOptions, buffer: &mut String) {
+/// Writes the comment before `item`. A line comment ends the line, so the
+/// item continues on the next one at `indent`.
+fn write_prefix_comment(
+    item: &ItemRef,
+    options: &FracturedJsonOptions,
+    indent: usize,
+    buffer: &mut Output<'_>,
+) {
     if options.comment_policy != crate::options::CommentPolicy::Preserve {
         return;
     }
 
     if let Some(comment) = item.prefix_comment() {
-        if options.comment_padding {
-            buffer.push(' ');
-        }
         buffer.push_str(comment);
-        if options.comment_padding {
+        if comment.starts_with("//") {
+            buffer.push_str(options.eol_string());
+            write_indent(options, indent, buffer);
+        } else if options.comment_padding {
             buffer.push(' ');
         }
     }
@@ -376,7 +385,7 @@ fn write_middle_comment(
     item: &ItemRef,
     options: &FracturedJsonOptions,
     indent: usize,
-    buffer: &mut String,
+    buffer: &mut Output<'_>,
 ) {
     if options.comment_policy != crate::options::CommentPolicy::Preserve {
         return;
@@ -391,7 +400,7 @@ fn write_middle_comment(
     }
 }
 
-fn write_postfix_comment(item: &ItemRef, options: &FracturedJsonOptions, buffer: &mut String) {
+fn write_postfix_comment(item: &ItemRef, options: &FracturedJsonOptions, buffer: &mut Output<'_>) {
     if options.comment_policy != crate::options::CommentPolicy::Preserve {
         return;
     }
@@ -404,59 +413,34 @@ fn write_postfix_comment(item: &ItemRef, options: &FracturedJsonOptions, buffer:
     }
 }
 
-fn calculate_items_per_row(item: &ItemRef, options: &FracturedJsonOptions, indent: usize) -> usize {
-    if item.is_empty() {
-        return 1;
-    }
-
-    let max_width = options.max_total_line_length - (indent + 1) * options.indent_spaces;
-    let avg_item_width: usize = item
-        .children
-        .iter()
-        .map(|c| c.minimum_total_length())
-        .sum::<usize>()
-        / item.children.len();
-
-    if avg_item_width == 0 {
-        return 1;
-    }
-
-    let items_per_row = max_width / avg_item_width.max(1);
-    items_per_row.max(1)
-}
-
 fn format_compact_array(
     item: &ItemRef,
     options: &FracturedJsonOptions,
     indent: usize,
-    buffer: &mut String,
+    items_per_row: usize,
+    column_widths: &[usize],
+    number_columns: &[Option<NumberColumn>],
+    buffer: &mut Output<'_>,
 ) {
-    write_indent(options, indent, buffer);
     buffer.push('[');
     buffer.push_str(options.eol_string());
 
-    let items_per_row = calculate_items_per_row(item, options, indent);
-    let mut current_row = 0;
-
-    for (i, child) in item.children.iter().enumerate() {
-        let row_idx = i / items_per_row;
-
-        if row_idx > current_row {
-            buffer.push(',');
-            buffer.push_str(options.eol_string());
-            current_row = row_idx;
-        }
-
+    for (row_idx, row) in item.children.chunks(items_per_row).enumerate() {
+        let is_last_row = (row_idx + 1) * items_per_row >= item.children.len();
         write_indent(options, indent + 1, buffer);
 
-        write_prefix_comment(child, options, buffer);
-        format_inline_value(child, options, 0, buffer);
-        write_postfix_comment(child, options, buffer);
-
-        if i < item.children.len() - 1 {
+        for (col_idx, child) in row.iter().enumerate() {
+            let len = write_cell(child, options, number_columns[col_idx].as_ref(), buffer);
+            if is_last_row && col_idx == row.len() - 1 {
+                break;
+            }
             buffer.push(',');
-            if options.comma_padding {
-                buffer.push(' ');
+            // Commas follow the values, with the padding after them
+            if col_idx < row.len() - 1 {
+                write_spaces(buffer, column_widths[col_idx].saturating_sub(len));
+                if options.comma_padding {
+                    buffer.push(' ');
+                }
             }
         }
 
@@ -467,130 +451,44 @@ fn format_compact_array(
     buffer.push(']');
 }
 
-fn calculate_table_column_widths(
-    items: &[&ItemRef],
-    indent: usize,
-    options: &FracturedJsonOptions,
-) -> Vec<usize> {
-    if items.is_empty() {
-        return Vec::new();
-    }
-
-    let column_count = get_column_count(items[0]);
-    let mut widths = vec![0; column_count];
-
-    for item in items {
-        // Calculate widths directly without allocating Vec<String>
-        match item.item_type() {
-            JsonItemType::Object => {
-                for (col_idx, child) in item.children.iter().enumerate() {
-                    let value_len = calculate_value_display_length(child) + 2; // +2 for quotes
-                    let name_len = child.name_length();
-                    // "name": value (simplified - doesn't account for escaping)
-                    let total_len = name_len + value_len + 4; // +4 for quotes, colon, space
-                    let indent_len = total_len + indent * options.indent_spaces;
-                    if col_idx < widths.len() {
-                        widths[col_idx] = widths[col_idx].max(indent_len);
-                    }
-                }
-            }
-            JsonItemType::Array => {
-                for (col_idx, child) in item.children.iter().enumerate() {
-                    let value_len = calculate_value_display_length(child) + 2; // +2 for brackets
-                    let indent_len = value_len + indent * options.indent_spaces;
-                    if col_idx < widths.len() {
-                        widths[col_idx] = widths[col_idx].max(indent_len);
-                    }
-                }
-            }
-            _ => {
-                let value_len = calculate_value_display_length(item) + 2; // +2 for quotes/brackets
-                let indent_len = value_len + indent * options.indent_spaces;
-                if !widths.is_empty() {
-                    widths[0] = widths[0].max(indent_len);
-                }
-            }
-        }
-    }
-
-    widths
-}
-
-/// Calculate display length without allocating (conservative estimate for strings)
-fn calculate_value_display_length(item: &ItemRef) -> usize {
-    match item.item_type() {
-        JsonItemType::String => {
-            // Estimate escaped length (most strings don't need escaping)
-            // Conservative: assume 20% overhead for escaping max
-            let base_len = item.value().len();
-            std::cmp::min(base_len * 2, base_len + base_len / 5 + 10)
-        }
-        JsonItemType::Number | JsonItemType::True | JsonItemType::False | JsonItemType::Null => {
-            item.value().len()
-        }
-        JsonItemType::Object => 2, // {}
-        JsonItemType::Array => 2,  // []
-        _ => 0,
-    }
-}
-
-fn get_column_count(item: &ItemRef) -> usize {
-    match item.item_type() {
-        JsonItemType::Object => item.children.len(),
-        JsonItemType::Array => item.children.len(),
-        _ => 1,
-    }
-}
-
-fn get_column_values(item: &ItemRef) -> Vec<String> {
-    match item.item_type() {
-        JsonItemType::Object => item.children.iter().map(format_value_simple).collect(),
-        JsonItemType::Array => item.children.iter().map(format_value_simple).collect(),
-        _ => vec![format_value_simple(item)],
-    }
-}
-
-fn format_value_simple(item: &ItemRef) -> String {
-    match item.item_type() {
-        JsonItemType::String => {
-            let escaped = escape_string(item.value());
-            format!("\"{}\"", escaped)
-        }
-        JsonItemType::Number => item.value().to_string(),
-        JsonItemType::True => "true".to_string(),
-        JsonItemType::False => "false".to_string(),
-        JsonItemType::Null => "null".to_string(),
-        JsonItemType::Object => "{}".to_string(),
-        JsonItemType::Array => "[]".to_string(),
-        _ => String::new(),
-    }
-}
-
-fn format_table_array(
+/// Writes an array, or an object of objects, one row per child with the
+/// cells of the rows lined up in columns.
+fn format_table(
     item: &ItemRef,
     options: &FracturedJsonOptions,
     indent: usize,
-    buffer: &mut String,
+    name_padding: usize,
+    column_widths: &[usize],
+    number_columns: &[Option<NumberColumn>],
+    buffer: &mut Output<'_>,
 ) {
-    write_indent(options, indent, buffer);
-    buffer.push('[');
+    let has_names = item.item_type() == JsonItemType::Object;
+    buffer.push(if has_names { '{' } else { '[' });
     buffer.push_str(options.eol_string());
 
-    let items: Vec<&ItemRef> = item.children.iter().collect();
-    let column_widths = calculate_table_column_widths(&items, indent + 1, options);
+    let separator = 1 + usize::from(options.comma_padding);
+    let placement = options.table_comma_placement;
+    let leading_commas = layout::leading_commas(options, column_widths.len());
+    // Writes the comma after a cell, and pads the cell to `width`
+    let write_comma = |buffer: &mut Output<'_>, width: usize, len: usize| {
+        if placement == TableCommaPlacement::AfterPadding {
+            write_spaces(buffer, width.saturating_sub(len));
+            buffer.push(',');
+        } else {
+            buffer.push(',');
+            write_spaces(buffer, width.saturating_sub(len));
+        }
+    };
 
-    for (i, child) in items.iter().enumerate() {
+    for (i, row) in item.children.iter().enumerate() {
+        let is_last = i == item.children.len() - 1;
         write_indent(options, indent + 1, buffer);
 
-        let values = get_column_values(child);
-        let is_last = i == items.len() - 1;
-
-        for (col_idx, value) in values.iter().enumerate() {
-            buffer.push_str(value);
-
-            if col_idx < values.len() - 1 {
-                let padding = column_widths[col_idx].saturating_sub(value.len());
-                write_spaces(buffer, padding);
+        if leading_commas {
+            // The first row is indented to line up with the commas of the rest
+            if i == 0 {
+                write_spaces(buffer, separator);
+            } else {
                 buffer.push(',');
                 if options.comma_padding {
                     buffer.push(' ');
@@ -598,35 +496,163 @@ fn format_table_array(
             }
         }
 
-        if !is_last && options.table_comma_placement == TableCommaPlacement::EndOfLine {
-            buffer.push(',');
+        if has_names {
+            write_quoted_property_name(row.name(), buffer);
+            let padding = name_padding.saturating_sub(row.name_length());
+            if options.colon_before_prop_name_padding {
+                buffer.push(':');
+                write_spaces(buffer, padding);
+            } else {
+                write_spaces(buffer, padding);
+                buffer.push(':');
+            }
+            if options.colon_padding {
+                buffer.push(' ');
+            }
+        }
+
+        let is_object = row.item_type() == JsonItemType::Object;
+        if is_object || row.item_type() == JsonItemType::Array {
+            buffer.push(if is_object { '{' } else { '[' });
+            if options.nested_bracket_padding {
+                buffer.push(' ');
+            }
+
+            let cells = layout::table_cells(row);
+            for (col_idx, &width) in column_widths.iter().enumerate() {
+                // Short rows are padded so that closing brackets line up
+                let Some(cell) = cells.get(col_idx) else {
+                    write_spaces(buffer, separator + width);
+                    continue;
+                };
+                if is_object {
+                    write_quoted_property_name(cell.name(), buffer);
+                    buffer.push(':');
+                    if options.colon_padding {
+                        buffer.push(' ');
+                    }
+                }
+                let len = write_cell(cell, options, number_columns[col_idx].as_ref(), buffer);
+                if col_idx < cells.len() - 1 {
+                    write_comma(buffer, width, len);
+                    if options.comma_padding {
+                        buffer.push(' ');
+                    }
+                } else {
+                    write_spaces(buffer, width.saturating_sub(len));
+                }
+            }
+
+            if options.nested_bracket_padding {
+                buffer.push(' ');
+            }
+            buffer.push(if is_object { '}' } else { ']' });
+            if !is_last && !leading_commas {
+                buffer.push(',');
+            }
+        } else {
+            let len = write_cell(row, options, number_columns[0].as_ref(), buffer);
+            if !is_last && placement == TableCommaPlacement::AfterPadding {
+                write_comma(buffer, column_widths[0], len);
+            } else if !is_last && !leading_commas {
+                // Nothing follows the comma to pad for
+                buffer.push(',');
+            }
         }
 
         buffer.push_str(options.eol_string());
     }
 
     write_indent(options, indent, buffer);
-    buffer.push(']');
+    buffer.push(if has_names { '}' } else { ']' });
+}
+
+/// Writes a compact array element or table cell on one line, leaving the
+/// padding after it to the caller, and returns the length written. Numbers
+/// in `numbers` come with the padding in front of them.
+fn write_cell(
+    cell: &ItemRef,
+    options: &FracturedJsonOptions,
+    numbers: Option<&NumberColumn>,
+    buffer: &mut Output<'_>,
+) -> usize {
+    match numbers {
+        Some(numbers) => {
+            let text = numbers.pad(cell.value());
+            let text = text.trim_end();
+            buffer.push_str(text);
+            text.len()
+        }
+        None => {
+            format_inline_value(cell, options, 0, buffer);
+            inline_len(cell, options)
+        }
+    }
+}
+
+/// Length of `item` written on one line, as compact array elements and
+/// table cells are. Items with comments are never written that way, so
+/// comments aren't counted.
+pub(crate) fn inline_len(item: &ItemRef, options: &FracturedJsonOptions) -> usize {
+    let is_object = match item.item_type() {
+        JsonItemType::Object => true,
+        JsonItemType::Array => false,
+        _ => return simple_value_len(item),
+    };
+    if item.is_empty() {
+        return 2 + usize::from(options.simple_bracket_padding);
+    }
+    let children: usize = item
+        .children
+        .iter()
+        .map(|c| {
+            let name = if is_object {
+                // Quotes, colon and the space after it
+                escaped_len(c.name()) + 3 + usize::from(options.colon_padding)
+            } else {
+                0
+            };
+            name + inline_len(c, options)
+        })
+        .sum();
+    let separators = (item.children.len() - 1) * (1 + usize::from(options.comma_padding));
+    2 + 2 * usize::from(options.nested_bracket_padding) + children + separators
+}
+
+/// Length of a scalar as written; empty brackets for containers.
+pub(crate) fn simple_value_len(item: &ItemRef) -> usize {
+    match item.item_type() {
+        JsonItemType::String => escaped_len(item.value()) + 2,
+        JsonItemType::Number => item.value().len(),
+        JsonItemType::True | JsonItemType::Null => 4,
+        JsonItemType::False => 5,
+        JsonItemType::Object | JsonItemType::Array => 2,
+        _ => 0,
+    }
 }
 
-fn format_table_object(
+/// Length of `escape_string(s)` without building it.
+pub(crate) fn escaped_len(s: &str) -> usize {
+    s.chars()
+        .map(|c| match c {
+            '\\' | '"' | '\n' | '\t' | '\r' | '\x08' | '\x0c' => 2,
+            c if c <= '\u{001f}' => 6,
+            c => c.len_utf8(),
+        })
+        .sum()
+}
+
+fn format_aligned_object(
     item: &ItemRef,
     options: &FracturedJsonOptions,
     indent: usize,
-    buffer: &mut String,
+    padding: usize,
+    buffer: &mut Output<'_>,
 ) {
-    write_indent(options, indent, buffer);
     buffer.push('{');
     buffer.push_str(options.eol_string());
 
-    let max_name_len = item
-        .children
-        .iter()
-        .map(|c| c.name_length())
-        .max()
-        .unwrap_or(0);
-
-    let padding = max_name_len.min(options.max_prop_name_padding);
+    let rendered = render_children_concurrently(item, options, indent);
 
     // Check if object has any non-comment items
     let has_properties = item
@@ -650,7 +676,7 @@ fn format_table_object(
 
         write_indent(options, indent + 1, buffer);
 
-        write_prefix_comment(child, options, buffer);
+        write_prefix_comment(child, options, indent + 1, buffer);
 
         if options.colon_before_prop_name_padding {
             write_quoted_property_name(child.name(), buffer);
@@ -667,15 +693,15 @@ fn format_table_object(
         }
 
         write_middle_comment(child, options, indent + 1, buffer);
-        format_item(child, options, indent + 1, buffer);
+        match &rendered {
+            Some(rendered) => buffer.push_str(&rendered[i]),
+            None => format_item(child, options, indent + 1, buffer),
+        }
         write_postfix_comment(child, options, buffer);
 
         let is_last = i == item.children.len() - 1;
         if !is_last || options.allow_trailing_commas {
             buffer.push(',');
-            if options.comma_padding {
-                buffer.push(' ');
-            }
         }
 
         buffer.push_str(options.eol_string());
@@ -685,13 +711,7 @@ fn format_table_object(
     buffer.push('}');
 }
 
-fn format_inline_array(
-    item: &ItemRef,
-    options: &FracturedJsonOptions,
-    indent: usize,
-    buffer: &mut String,
-) {
-    write_indent(options, indent, buffer);
+fn format_inline_array(item: &ItemRef, options: &FracturedJsonOptions, buffer: &mut Output<'_>) {
     buffer.push('[');
 
     if !item.is_empty() {
@@ -707,7 +727,7 @@ fn format_inline_array(
                 }
             }
 
-            write_prefix_comment(child, options, buffer);
+            write_prefix_comment(child, options, 0, buffer);
             format_inline_value(child, options, 0, buffer);
             write_postfix_comment(child, options, buffer);
         }
@@ -726,44 +746,43 @@ fn format_expanded_array(
     item: &ItemRef,
     options: &FracturedJsonOptions,
     indent: usize,
-    buffer: &mut String,
+    numbers: Option<&NumberColumn>,
+    buffer: &mut Output<'_>,
 ) {
-    write_indent(options, indent, buffer);
     buffer.push('[');
     buffer.push_str(options.eol_string());
 
-    let should_align_numbers = options.number_list_alignment != NumberListAlignment::None
-        && item
-            .children
-            .iter()
-            .all(|c| c.item_type() == JsonItemType::Number);
-
-    let all_numbers: Vec<&str> = if should_align_numbers {
-        item.children.iter().map(|c| c.value()).collect()
+    let rendered = if numbers.is_some() {
+        None
     } else {
-        Vec::new()
+        render_children_concurrently(item, options, indent)
     };
 
     for (i, child) in item.children.iter().enumerate() {
         write_indent(options, indent + 1, buffer);
 
-        write_prefix_comment(child, options, buffer);
+        write_prefix_comment(child, options, indent + 1, buffer);
 
-        if should_align_numbers && child.item_type() == JsonItemType::Number {
-            let aligned = format_number_aligned(child.value(), options, &all_numbers);
-            buffer.push_str(&aligned);
+        let is_last = i == item.children.len() - 1;
+        let comma = !is_last || (options.allow_trailing_commas && !item.is_empty());
+        if let Some(numbers) = numbers {
+            let text = numbers.pad(child.value());
+            // Padding only lines up what follows on the line
+            if comma || child.postfix_comment().is_some() {
+                buffer.push_str(&text);
+            } else {
+                buffer.push_str(text.trim_end());
+            }
+        } else if let Some(rendered) = &rendered {
+            buffer.push_str(&rendered[i]);
         } else {
             format_item(child, options, indent + 1, buffer);
         }
 
         write_postfix_comment(child, options, buffer);
 
-        let is_last = i == item.children.len() - 1;
-        if !is_last || (options.allow_trailing_commas && !item.is_empty()) {
+        if comma {
             buffer.push(',');
-            if options.comma_padding {
-                buffer.push(' ');
-            }
         }
 
         buffer.push_str(options.eol_string());
@@ -777,9 +796,8 @@ fn format_inline_object(
     item: &ItemRef,
     options: &FracturedJsonOptions,
     indent: usize,
-    buffer: &mut String,
+    buffer: &mut Output<'_>,
 ) {
-    write_indent(options, indent, buffer);
     buffer.push('{');
 
     if !item.is_empty() {
@@ -818,7 +836,7 @@ fn format_inline_object(
                 }
             }
 
-            write_prefix_comment(child, options, buffer);
+            write_prefix_comment(child, options, indent, buffer);
             write_quoted_property_name(child.name(), buffer);
             buffer.push(':');
 
@@ -845,12 +863,13 @@ fn format_expanded_object(
     item: &ItemRef,
     options: &FracturedJsonOptions,
     indent: usize,
-    buffer: &mut String,
+    buffer: &mut Output<'_>,
 ) {
-    write_indent(options, indent, buffer);
     buffer.push('{');
     buffer.push_str(options.eol_string());
 
+    let rendered = render_children_concurrently(item, options, indent);
+
     // Check if object has any non-comment items
     let has_properties = item
         .children
@@ -873,7 +892,7 @@ fn format_expanded_object(
 
         write_indent(options, indent + 1, buffer);
 
-        write_prefix_comment(child, options, buffer);
+        write_prefix_comment(child, options, indent + 1, buffer);
         write_quoted_property_name(child.name(), buffer);
         buffer.push(':');
 
@@ -882,15 +901,15 @@ fn format_expanded_object(
         }
 
         write_middle_comment(child, options, indent + 1, buffer);
-        format_item(child, options, indent + 1, buffer);
+        match &rendered {
+            Some(rendered) => buffer.push_str(&rendered[i]),
+            None => format_item(child, options, indent + 1, buffer),
+        }
         write_postfix_comment(child, options, buffer);
 
         let is_last = i == item.children.len() - 1;
         if !is_last || (options.allow_trailing_commas && !item.is_empty()) {
             buffer.push(',');
-            if options.comma_padding {
-                buffer.push(' ');
-            }
         }
 
         buffer.push_str(options.eol_string());
@@ -904,7 +923,7 @@ fn format_inline_value(
     item: &ItemRef,
     options: &FracturedJsonOptions,
     indent: usize,
-    buffer: &mut String,
+    buffer: &mut Output<'_>,
 ) {
     match item.item_type() {
         JsonItemType::Array | JsonItemType::Object => {
@@ -933,3 +952,49 @@ fn format_inline_value(
         JsonItemType::BlankLine => {}
     }
 }
+
+#[cfg(all(test, feature = "rayon"))]
+mod tests {
+    use super::*;
+    use crate::transform::{parse, transform};
+
+    #[test]
+    fn test_concurrent_children_match_sequential() {
+        let rows: Vec<String> = (0..300)
+            .map(|i| match i % 3 {
+                0 => format!(r#"{{"id": {}, "tags": ["a", "b"]}}"#, i),
+                1 => format!(r#"[{}, "x", null, {{"deep": [{}]}}]"#, i, i),
+                _ => format!(r#""row {}""#, i),
+            })
+            .collect();
+        let options = FracturedJsonOptions::default();
+
+        for input in [
+            format!("[{}]", rows.join(",")),
+            format!(
+                "{{{}}}",
+                rows.iter()
+                    .enumerate()
+                    .map(|(i, row)| format!(r#""k{}": {}"#, i, row))
+                    .collect::<Vec<_>>()
+                    .join(",")
+            ),
+        ] {
+            let item = transform(&input, parse(&input).unwrap());
+            let computed = ItemRef::from_root(&item, &options);
+            let sequential: Vec<String> = computed
+                .children
+                .iter()
+                .map(|child| {
+                    let mut out = Output::new(String::new(), None);
+                    format_item(child, &options, 1, &mut out);
+                    out.buf
+                })
+                .collect();
+
+            let concurrent = render_children_concurrently(&computed, &options, 0).unwrap();
+            assert_eq!(concurrent, sequential);
+            assert!(render_children_concurrently(&computed, &options, 1).is_none());
+        }
+    }
+}
@@ -0,0 +1,764 @@
+//! Layout planning: decides once, top-down, how every container is written,
+//! so that formatting is pure emission.
+
+use crate::computed::ItemRef;
+use crate::formatter::{escaped_len, inline_len};
+use crate::numbers::{is_integer, NumberColumn};
+use crate::options::{FracturedJsonOptions, NumberListAlignment, TableCommaPlacement};
+use crate::types::JsonItemType;
+use std::fmt;
+
+/// How an item is written out.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum Layout {
+    /// A scalar, comment or blank line.
+    #[default]
+    Value,
+    /// The whole container on one line.
+    Inline,
+    /// Array elements in rows of `items_per_row`, each column padded to its
+    /// entry in `column_widths`. `number_columns` has an entry for each
+    /// column, set when the elements are numbers.
+    CompactArray {
+        items_per_row: usize,
+        column_widths: Vec<usize>,
+        number_columns: Vec<Option<NumberColumn>>,
+    },
+    /// One row per element, or per property of an object of objects, with
+    /// cells padded to `column_widths`. `number_columns` has an entry for
+    /// each column, set for the columns of numbers. Property names are
+    /// padded to `name_padding`, which is 0 for arrays.
+    Table {
+        name_padding: usize,
+        column_widths: Vec<usize>,
+        number_columns: Vec<Option<NumberColumn>>,
+    },
+    /// One property per line, names padded to `name_padding`.
+    AlignedObject { name_padding: usize },
+    /// One child per line. `numbers` is set when the children are numbers
+    /// aligned per `number_list_alignment`.
+    Expanded { numbers: Option<NumberColumn> },
+}
+
+impl Layout {
+    /// Short name of the layout kind.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Layout::Value => "value",
+            Layout::Inline => "inline",
+            Layout::CompactArray { .. } => "compact-array",
+            Layout::Table { .. } => "table",
+            Layout::AlignedObject { .. } => "aligned-object",
+            Layout::Expanded { .. } => "expanded",
+        }
+    }
+}
+
+/// Plans `item`, written at `indent`, and everything below it.
+pub(crate) fn plan(item: &mut ItemRef<'_>, options: &FracturedJsonOptions, indent: usize) {
+    let layout = choose_layout(item, options, indent, &mut |_, _| {});
+
+    if writes_children_inline(&layout) {
+        for child in &mut item.children {
+            plan_inline(child);
+        }
+    } else {
+        let child_indent = child_indent(&layout, indent);
+        for child in &mut item.children {
+            plan(child, options, child_indent);
+        }
+    }
+
+    item.computed.layout = layout;
+}
+
+/// Plans `item` and everything below it on one line.
+fn plan_inline(item: &mut ItemRef<'_>) {
+    if matches!(item.item_type(), JsonItemType::Array | JsonItemType::Object) {
+        item.computed.layout = Layout::Inline;
+    }
+    for child in &mut item.children {
+        plan_inline(child);
+    }
+}
+
+/// Whether a container laid out as `layout` writes each of its children on
+/// one line: inline containers, and the elements of compact arrays and
+/// table rows, which are sized as they are planned.
+pub(crate) fn writes_children_inline(layout: &Layout) -> bool {
+    matches!(
+        layout,
+        Layout::Inline | Layout::CompactArray { .. } | Layout::Table { .. }
+    )
+}
+
+/// Why a candidate layout was passed over for a container.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Rejection<'a> {
+    /// A child has comments or must itself span several lines.
+    RequiresMultipleLines,
+    /// The container carries comments of its own.
+    HasComments,
+    /// The container has no children.
+    Empty,
+    /// Nesting depth exceeds the limit set by `option`.
+    Complexity {
+        complexity: u32,
+        max: u32,
+        option: &'static str,
+    },
+    /// The one-line form, indent included, is longer than `max`.
+    Length { length: usize, max: usize },
+    /// Fewer children than `min_compact_array_row_items`.
+    TooFewItems { count: usize, min: usize },
+    /// Only one element fits per row, which is an expanded array without
+    /// its alignment.
+    OnePerRow,
+    /// Row `row` can't be a table row (an empty container or a comment).
+    UntabularRow { row: usize },
+    /// Row `row` is a different kind of value from the first row.
+    MixedRowTypes { row: usize },
+    /// Object row `row` has different keys, or keys in a different order,
+    /// from the first row.
+    RowKeysDiffer { row: usize },
+    /// Property `property` isn't a non-empty object.
+    NotObjectRow { property: &'a str },
+    /// Property `property` has different keys from property `first`.
+    KeysDiffer { property: &'a str, first: &'a str },
+}
+
+impl fmt::Display for Rejection<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rejection::RequiresMultipleLines => {
+                write!(f, "contents have comments or span multiple lines")
+            }
+            Rejection::HasComments => write!(f, "has comments"),
+            Rejection::Empty => write!(f, "is empty"),
+            Rejection::Complexity {
+                complexity,
+                max,
+                option,
+            } => write!(f, "complexity {complexity} > {option} {max}"),
+            Rejection::Length { length, max } => {
+                write!(f, "length {length} > max_total_line_length {max}")
+            }
+            Rejection::TooFewItems { count, min } => {
+                write!(f, "{count} items < min_compact_array_row_items {min}")
+            }
+            Rejection::OnePerRow => write!(f, "only one element fits per row"),
+            Rejection::UntabularRow { row } => {
+                write!(f, "row {row} is an empty container or a comment")
+            }
+            Rejection::MixedRowTypes { row } => {
+                write!(f, "row {row} is a different type from row 0")
+            }
+            Rejection::RowKeysDiffer { row } => {
+                write!(f, "row {row} has different keys from row 0")
+            }
+            Rejection::NotObjectRow { property } => {
+                write!(f, "property \"{property}\" is not a non-empty object")
+            }
+            Rejection::KeysDiffer { property, first } => write!(
+                f,
+                "property \"{property}\" has different keys from \"{first}\""
+            ),
+        }
+    }
+}
+
+/// Chooses the layout for `item` at `indent`, passing each candidate layout
+/// it rules out to `reject`.
+pub(crate) fn choose_layout<'a>(
+    item: &ItemRef<'a>,
+    options: &FracturedJsonOptions,
+    indent: usize,
+    reject: &mut dyn FnMut(&'static str, Rejection<'a>),
+) -> Layout {
+    let mut accept = |candidate: &'static str, check: Result<Layout, Rejection<'a>>| match check {
+        Ok(layout) => Some(layout),
+        Err(rejection) => {
+            reject(candidate, rejection);
+            None
+        }
+    };
+
+    match item.item_type() {
+        // Expanding an empty container only adds a line break between its
+        // brackets
+        JsonItemType::Array | JsonItemType::Object if item.is_empty() => Layout::Inline,
+        JsonItemType::Array => accept("inline", check_inline(item, options, indent))
+            .or_else(|| {
+                accept(
+                    "compact-array",
+                    check_compact_array(item, options)
+                        .and_then(|()| compact_array_layout(item, options, indent)),
+                )
+            })
+            .or_else(|| accept("table", table_layout(item, options, indent)))
+            .unwrap_or_else(|| Layout::Expanded {
+                numbers: number_column(&item.children, options),
+            }),
+        JsonItemType::Object => accept("inline", check_inline(item, options, indent))
+            .or_else(|| accept("table", table_layout(item, options, indent)))
+            .or_else(|| {
+                accept(
+                    "aligned-object",
+                    check_table(item, options).map(|()| Layout::AlignedObject {
+                        name_padding: name_padding(item, options),
+                    }),
+                )
+            })
+            .unwrap_or(Layout::Expanded { numbers: None }),
+        _ => Layout::Value,
+    }
+}
+
+/// Indent that the children of a container laid out as `layout` at
+/// `indent` are planned at.
+pub(crate) fn child_indent(layout: &Layout, indent: usize) -> usize {
+    // Inline containers and compact rows write nested containers as if
+    // they were at the top level
+    match layout {
+        Layout::Inline | Layout::CompactArray { .. } => 0,
+        _ => indent + 1,
+    }
+}
+
+fn check_inline<'a>(
+    item: &ItemRef<'a>,
+    options: &FracturedJsonOptions,
+    indent: usize,
+) -> Result<Layout, Rejection<'a>> {
+    if item.requires_multiple_lines() {
+        return Err(Rejection::RequiresMultipleLines);
+    }
+
+    if item.has_comments() {
+        return Err(Rejection::HasComments);
+    }
+
+    if item.complexity() > options.max_inline_complexity {
+        return Err(Rejection::Complexity {
+            complexity: item.complexity(),
+            max: options.max_inline_complexity,
+            option: "max_inline_complexity",
+        });
+    }
+
+    let length = item.minimum_total_length() + indent * options.indent_spaces;
+    if length > options.max_total_line_length {
+        return Err(Rejection::Length {
+            length,
+            max: options.max_total_line_length,
+        });
+    }
+
+    Ok(Layout::Inline)
+}
+
+fn check_compact_array<'a>(
+    item: &ItemRef<'a>,
+    options: &FracturedJsonOptions,
+) -> Result<(), Rejection<'a>> {
+    if item.is_empty() {
+        return Err(Rejection::Empty);
+    }
+
+    if item.complexity() > options.max_compact_array_complexity {
+        return Err(Rejection::Complexity {
+            complexity: item.complexity(),
+            max: options.max_compact_array_complexity,
+            option: "max_compact_array_complexity",
+        });
+    }
+
+    if item.requires_multiple_lines() || has_standalone_comments(item) {
+        return Err(Rejection::RequiresMultipleLines);
+    }
+
+    if item.has_comments() {
+        return Err(Rejection::HasComments);
+    }
+
+    if item.children.len() < options.min_compact_array_row_items {
+        return Err(Rejection::TooFewItems {
+            count: item.children.len(),
+            min: options.min_compact_array_row_items,
+        });
+    }
+
+    Ok(())
+}
+
+fn check_table<'a>(
+    item: &ItemRef<'a>,
+    options: &FracturedJsonOptions,
+) -> Result<(), Rejection<'a>> {
+    let complexity = |item: &ItemRef<'a>| {
+        if item.complexity() > options.max_table_row_complexity {
+            Err(Rejection::Complexity {
+                complexity: item.complexity(),
+                max: options.max_table_row_complexity,
+                option: "max_table_row_complexity",
+            })
+        } else {
+            Ok(())
+        }
+    };
+
+    if item.item_type() == JsonItemType::Array {
+        // Don't use table format if comments are present
+        if item.requires_multiple_lines() {
+            return Err(Rejection::RequiresMultipleLines);
+        }
+
+        if item.is_empty() {
+            return Err(Rejection::Empty);
+        }
+
+        complexity(item)?;
+        return check_table_array(item);
+    }
+
+    if item.is_empty() {
+        return Err(Rejection::Empty);
+    }
+
+    complexity(item)?;
+    check_table_object(item)
+}
+
+fn check_table_array<'a>(item: &ItemRef<'a>) -> Result<(), Rejection<'a>> {
+    let first_type = get_item_type_for_table(&item.children[0]);
+    if first_type.is_none() {
+        return Err(Rejection::UntabularRow { row: 0 });
+    }
+
+    match item
+        .children
+        .iter()
+        .position(|c| get_item_type_for_table(c) != first_type)
+    {
+        Some(row) if get_item_type_for_table(&item.children[row]).is_none() => {
+            Err(Rejection::UntabularRow { row })
+        }
+        Some(row) => Err(Rejection::MixedRowTypes { row }),
+        None if first_type == Some(JsonItemType::Object) => {
+            let first = &item.children[0];
+            match item.children.iter().position(|c| !same_keys(c, first)) {
+                Some(row) => Err(Rejection::RowKeysDiffer { row }),
+                None => Ok(()),
+            }
+        }
+        None => Ok(()),
+    }
+}
+
+/// Every child is an object with the same, non-empty list of property names.
+fn check_table_object<'a>(item: &ItemRef<'a>) -> Result<(), Rejection<'a>> {
+    let first = &item.children[0];
+    for c in &item.children {
+        if c.item_type() != JsonItemType::Object || c.children.is_empty() {
+            return Err(Rejection::NotObjectRow {
+                property: c.item.name.as_ref(),
+            });
+        }
+        if !same_keys(c, first) {
+            return Err(Rejection::KeysDiffer {
+                property: c.item.name.as_ref(),
+                first: first.item.name.as_ref(),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Whether objects `a` and `b` have the same property names in the same
+/// order.
+fn same_keys(a: &ItemRef, b: &ItemRef) -> bool {
+    a.children.len() == b.children.len()
+        && a.children
+            .iter()
+            .zip(&b.children)
+            .all(|(a, b)| a.name() == b.name())
+}
+
+/// Whether `item` holds a comment of its own, which can't share a line with
+/// the elements after it.
+fn has_standalone_comments(item: &ItemRef) -> bool {
+    item.children.iter().any(|c| c.item_type().is_comment())
+}
+
+fn get_item_type_for_table(item: &ItemRef) -> Option<JsonItemType> {
+    if has_standalone_comments(item) {
+        return None;
+    }
+
+    match item.item_type() {
+        JsonItemType::Object => {
+            if item.is_empty() {
+                None
+            } else {
+                Some(JsonItemType::Object)
+            }
+        }
+        JsonItemType::Array => {
+            if item.is_empty() {
+                None
+            } else {
+                Some(JsonItemType::Array)
+            }
+        }
+        JsonItemType::String
+        | JsonItemType::Number
+        | JsonItemType::True
+        | JsonItemType::False
+        | JsonItemType::Null => Some(item.item_type()),
+        _ => None,
+    }
+}
+
+/// Lays out the elements of `item` in as many columns as fit in
+/// `max_total_line_length`, each column as wide as its widest element.
+/// Fails if fewer than two fit, leaving the array to the later layouts.
+fn compact_array_layout<'a>(
+    item: &ItemRef<'a>,
+    options: &FracturedJsonOptions,
+    indent: usize,
+) -> Result<Layout, Rejection<'a>> {
+    let numbers = number_column(&item.children, options);
+    let lengths: Vec<usize> = item
+        .children
+        .iter()
+        .map(|c| match &numbers {
+            Some(numbers) => numbers.text(c.value()).len(),
+            None => inline_len(c, options),
+        })
+        .collect();
+    let available = options
+        .max_total_line_length
+        .saturating_sub((indent + 1) * options.indent_spaces);
+    let separator = 1 + usize::from(options.comma_padding);
+
+    // No row holds more than the shortest elements would fill
+    let shortest = lengths.iter().copied().min().unwrap_or(0);
+    let mut items_per_row =
+        ((available + separator) / (shortest + separator)).clamp(1, lengths.len());
+    while items_per_row > 1 {
+        let (column_widths, number_columns): (Vec<usize>, Vec<Option<NumberColumn>>) = (0
+            ..items_per_row)
+            .map(|col_idx| match &numbers {
+                Some(numbers) => {
+                    let column = numbers.measure(
+                        item.children
+                            .iter()
+                            .skip(col_idx)
+                            .step_by(items_per_row)
+                            .map(|c| c.value()),
+                    );
+                    (column.width, Some(column))
+                }
+                None => {
+                    let width = lengths.iter().skip(col_idx).step_by(items_per_row).max();
+                    (width.copied().unwrap_or(0), None)
+                }
+            })
+            .unzip();
+
+        // Separators between the elements, and a comma after the last
+        let row_length = column_widths.iter().sum::<usize>() + (items_per_row - 1) * separator + 1;
+        if row_length <= available {
+            return Ok(Layout::CompactArray {
+                items_per_row,
+                column_widths,
+                number_columns,
+            });
+        }
+        items_per_row -= 1;
+    }
+    Err(Rejection::OnePerRow)
+}
+
+/// Whether the rows of a table of `columns` columns start with the comma
+/// separating them from the row before, per `table_comma_placement`.
+pub(crate) fn leading_commas(options: &FracturedJsonOptions, columns: usize) -> bool {
+    options.table_comma_placement == TableCommaPlacement::NextLine && columns > 1
+}
+
+/// Width the property names of `item` are padded to.
+fn name_padding(item: &ItemRef, options: &FracturedJsonOptions) -> usize {
+    let max_name_len = item
+        .children
+        .iter()
+        .map(|c| c.name_length())
+        .max()
+        .unwrap_or(0);
+    max_name_len.min(options.max_prop_name_padding)
+}
+
+/// Lays out `item` as a table, if it passes [`check_table`] and its widest
+/// row fits in `max_total_line_length`. The rows of an object are the
+/// values of its properties.
+fn table_layout<'a>(
+    item: &ItemRef<'a>,
+    options: &FracturedJsonOptions,
+    indent: usize,
+) -> Result<Layout, Rejection<'a>> {
+    check_table(item, options)?;
+    // An aligned object keeps the comments of its properties, but a row
+    // has nowhere to put them
+    if item.requires_multiple_lines() {
+        return Err(Rejection::RequiresMultipleLines);
+    }
+
+    let number_columns = table_number_columns(item, options);
+    let mut column_widths = vec![0; number_columns.len()];
+    for row in &item.children {
+        for (col_idx, cell) in table_cells(row).iter().enumerate() {
+            let width = match &number_columns[col_idx] {
+                Some(numbers) => numbers.width,
+                None => inline_len(cell, options),
+            };
+            column_widths[col_idx] = column_widths[col_idx].max(width);
+        }
+    }
+
+    let separator = 1 + usize::from(options.comma_padding);
+    let cells = column_widths.iter().sum::<usize>() + (column_widths.len() - 1) * separator;
+    let brackets = 2 + 2 * usize::from(options.nested_bracket_padding);
+    let first = &item.children[0];
+    let row_length = match first.item_type() {
+        // Every row has the same keys as the first
+        JsonItemType::Object => {
+            let names: usize = first
+                .children
+                .iter()
+                .map(|c| escaped_len(c.name()) + 3 + usize::from(options.colon_padding))
+                .sum();
+            brackets + names + cells
+        }
+        JsonItemType::Array => brackets + cells,
+        _ => cells,
+    };
+    let (name_padding, row_length) = if item.item_type() == JsonItemType::Object {
+        let name_padding = name_padding(item, options);
+        // Padded names, with quotes, colon and the space after it
+        let names = item
+            .children
+            .iter()
+            .map(|row| escaped_len(row.name()) + name_padding.saturating_sub(row.name_length()))
+            .max()
+            .unwrap_or(0);
+        let name_length = names + 3 + usize::from(options.colon_padding);
+        (name_padding, name_length + row_length)
+    } else {
+        (0, row_length)
+    };
+    // Every row but the last has a comma after it, or with leading commas,
+    // every row but the first has one and a space in front of it
+    let comma = if leading_commas(options, column_widths.len()) {
+        separator
+    } else {
+        1
+    };
+    let length = (indent + 1) * options.indent_spaces + row_length + comma;
+    if length > options.max_total_line_length {
+        return Err(Rejection::Length {
+            length,
+            max: options.max_total_line_length,
+        });
+    }
+
+    Ok(Layout::Table {
+        name_padding,
+        column_widths,
+        number_columns,
+    })
+}
+
+/// Plans `items` as a column of numbers, if they all are numbers.
+fn number_column(items: &[ItemRef], options: &FracturedJsonOptions) -> Option<NumberColumn> {
+    let all_numbers = items.iter().all(|c| c.item_type() == JsonItemType::Number);
+    if !all_numbers {
+        return None;
+    }
+    NumberColumn::plan(
+        items.iter().map(|c| c.value()),
+        options.number_list_alignment,
+    )
+}
+
+/// The cells of a table row: the children of a container, or a lone value.
+pub(crate) fn table_cells<'r, 'a>(row: &'r ItemRef<'a>) -> &'r [ItemRef<'a>] {
+    match row.item_type() {
+        JsonItemType::Object | JsonItemType::Array => row.children.as_slice(),
+        _ => std::slice::from_ref(row),
+    }
+}
+
+/// Plans each column of a table, setting those in which every cell is a
+/// number. Array rows may be short, so a column needn't reach every row.
+/// Columns of integers are right-aligned even when other numbers aren't
+/// aligned, so their magnitudes can be compared at a glance.
+fn table_number_columns(
+    item: &ItemRef,
+    options: &FracturedJsonOptions,
+) -> Vec<Option<NumberColumn>> {
+    let column_count = item
+        .children
+        .iter()
+        .map(|row| table_cells(row).len())
+        .max()
+        .unwrap_or(0);
+    (0..column_count)
+        .map(|col_idx| {
+            let cells = item
+                .children
+                .iter()
+                .filter_map(|row| table_cells(row).get(col_idx));
+            if !cells
+                .clone()
+                .all(|cell| cell.item_type() == JsonItemType::Number)
+            {
+                return None;
+            }
+            let alignment = match options.number_list_alignment {
+                NumberListAlignment::None if cells.clone().all(|cell| is_integer(cell.value())) => {
+                    NumberListAlignment::Right
+                }
+                alignment => alignment,
+            };
+            NumberColumn::plan(cells.map(|cell| cell.value()), alignment)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transform::{parse, transform};
+
+    fn root_layout(input: &str, options: &FracturedJsonOptions) -> Layout {
+        let item = transform(input, parse(input).unwrap());
+        ItemRef::from_root(&item, options).layout().clone()
+    }
+
+    #[test]
+    fn test_layout_choices() {
+        let options = FracturedJsonOptions::default();
+        assert_eq!(root_layout("[1, 2, 3]", &options), Layout::Inline);
+        assert_eq!(root_layout("42", &options), Layout::Value);
+
+        let long_numbers = format!("[{}]", vec!["12345"; 40].join(", "));
+        assert!(matches!(
+            root_layout(&long_numbers, &options),
+            Layout::CompactArray { items_per_row, .. } if items_per_row > 1
+        ));
+
+        let records = format!("[{}]", vec![r#"["abc", 1, true]"#; 40].join(", "));
+        assert!(matches!(
+            root_layout(&records, &FracturedJsonOptions { max_compact_array_complexity: 0, max_table_row_complexity: 2, ..options.clone() }),
+            Layout::Table { column_widths, .. } if column_widths.len() == 3
+        ));
+    }
+
+    #[test]
+    fn test_number_width_is_planned() {
+        let options = FracturedJsonOptions {
+            number_list_alignment: NumberListAlignment::Decimal,
+            max_inline_complexity: 0,
+            max_compact_array_complexity: 0,
+            max_table_row_complexity: 0,
+            ..FracturedJsonOptions::default()
+        };
+        assert_eq!(
+            root_layout("[1.5, 100, -20.25]", &options),
+            Layout::Expanded {
+                numbers: Some(NumberColumn {
+                    decimals: None,
+                    integer_width: 3,
+                    fraction_width: 3,
+                    width: 6,
+                    alignment: NumberListAlignment::Decimal
+                })
+            }
+        );
+    }
+
+    #[test]
+    fn test_table_rejections() {
+        let options = FracturedJsonOptions {
+            max_inline_complexity: 0,
+            max_compact_array_complexity: 0,
+            max_table_row_complexity: 2,
+            ..FracturedJsonOptions::default()
+        };
+        let rejections = |input: &str, options: &FracturedJsonOptions| {
+            let item = transform(input, parse(input).unwrap());
+            let computed = ItemRef::from_root(&item, options);
+            let mut reasons = Vec::new();
+            choose_layout(&computed, options, 0, &mut |layout, reason| {
+                reasons.push(format!("{layout}: {reason}"))
+            });
+            reasons
+        };
+
+        let reordered = r#"[{"a": 1, "b": 2}, {"b": 3, "a": 4}]"#;
+        assert_eq!(
+            rejections(reordered, &options)[2],
+            "table: row 1 has different keys from row 0"
+        );
+
+        // `    { "a": 1, "b": 2 },` is 23 characters
+        let rows = r#"[{"a": 1, "b": 2}, {"a": 3, "b": 4}]"#;
+        assert!(matches!(root_layout(rows, &options), Layout::Table { .. }));
+        let narrow = FracturedJsonOptions {
+            max_total_line_length: 22,
+            ..options
+        };
+        assert_eq!(
+            rejections(rows, &narrow)[2],
+            "table: length 23 > max_total_line_length 22"
+        );
+    }
+
+    #[test]
+    fn test_cells_measured_as_written() {
+        let options = FracturedJsonOptions {
+            max_inline_complexity: 4,
+            ..FracturedJsonOptions::default()
+        };
+        let input = r#"[[], {}, "t\"ab\t", [1, {"k\n": [null]}], {"x": false}]"#;
+        let item = transform(input, parse(input).unwrap());
+        let computed = ItemRef::from_root(&item, &options);
+        let written = crate::formatter::format_computed(&computed, &options);
+        let cells: usize = computed
+            .children
+            .iter()
+            .map(|c| inline_len(c, &options))
+            .sum();
+        // Brackets with their padding, and separators
+        assert_eq!(written.len(), cells + 4 + 4 * 2);
+    }
+
+    #[test]
+    fn test_children_planned_at_their_indent() {
+        let options = FracturedJsonOptions {
+            max_total_line_length: 30,
+            ..FracturedJsonOptions::default()
+        };
+        // Fits at the top level but not nested one level deeper
+        let inner = r#"{"abcdefgh": "123456789012345"}"#;
+        assert_eq!(root_layout(inner, &options), Layout::Inline);
+
+        let outer = format!(r#"{{"x": {}, "long": "{}"}}"#, inner, "y".repeat(30));
+        let item = transform(&outer, parse(&outer).unwrap());
+        let computed = ItemRef::from_root(&item, &options);
+        assert!(matches!(computed.layout(), Layout::Expanded { .. }));
+        assert!(matches!(
+            computed.children[0].layout(),
+            Layout::Expanded { .. }
+        ));
+    }
+}
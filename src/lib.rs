@@ -1,27 +1,74 @@
+pub mod canonical;
 pub mod computed;
+pub mod config;
+pub mod diagnostics;
 pub mod error;
+pub mod explain;
 pub mod formatter;
+pub mod layout;
+pub mod minify;
+pub mod numbers;
 pub mod options;
+mod parallel;
+#[cfg(feature = "serde")]
+pub mod ser;
+pub mod stream;
 pub mod transform;
 pub mod types;
+#[cfg(feature = "serde_json")]
+pub mod value;
 
+pub use canonical::{canonicalize, canonicalize_item, canonicalize_item_with_diagnostics};
+pub use config::ConfigFile;
+pub use diagnostics::{Diagnostic, DiagnosticCode, Severity};
 pub use error::{FracturedJsonError, Result};
-pub use formatter::format;
+pub use explain::{explain, Explanation, RejectedLayout};
+pub use formatter::{
+    format, format_computed, format_computed_to_writer, format_to_fmt_writer, format_to_writer,
+};
+pub use layout::{Layout, Rejection};
+pub use minify::{minify, minify_item};
 pub use options::{
-    CommentPolicy, EolStyle, FracturedJsonOptions, NumberListAlignment, TableCommaPlacement,
+    CommentPolicy, DuplicateKeyPolicy, EolStyle, FracturedJsonOptions, NumberListAlignment, Preset,
+    SortKeys, TableCommaPlacement,
 };
-pub use transform::transform;
+#[cfg(feature = "serde")]
+pub use ser::{to_item, to_string_fractured, to_writer_fractured};
+pub use stream::format_reader_low_memory;
+pub use transform::{parse_document, transform};
 pub use types::{InputPosition, JsonItem, JsonItemType};
-
-use jsonc_parser::{cst::CstRootNode, ParseOptions};
+#[cfg(feature = "serde_json")]
+pub use value::format_value;
 
 pub fn format_jsonc(input: &str, options: &FracturedJsonOptions) -> Result<String> {
-    let parse_options = ParseOptions::default();
-    let cst = CstRootNode::parse(input, &parse_options)?;
-    let json_item = transform(&cst);
+    let (json_item, _) = transform::parse_document(input, options)?;
     Ok(format(&json_item, options))
 }
 
+/// Like [`format_jsonc`], also returning the warnings found in `input` and
+/// in the output made from it, in input order.
+pub fn format_jsonc_with_diagnostics(
+    input: &str,
+    options: &FracturedJsonOptions,
+) -> Result<(String, Vec<Diagnostic>)> {
+    let (json_item, mut diagnostics) = transform::parse_document(input, options)?;
+    let computed = computed::ItemRef::from_root(&json_item, options);
+    diagnostics.extend(diagnostics::line_length_diagnostics(&computed, options));
+    diagnostics.sort_by_key(|d| d.position.map(|p| (p.line, p.column)));
+    Ok((format_computed(&computed, options), diagnostics))
+}
+
+/// Formats `input` straight into `writer` without building the output string.
+pub fn format_jsonc_to_writer<W: std::io::Write + ?Sized>(
+    input: &str,
+    options: &FracturedJsonOptions,
+    writer: &mut W,
+) -> Result<()> {
+    let (json_item, _) = transform::parse_document(input, options)?;
+    format_to_writer(&json_item, options, writer)?;
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -124,6 +171,218 @@ mod tests {
         assert!(result.contains("10.25"));
     }
 
+    #[test]
+    fn test_number_alignment_normalize() {
+        let input = "[1, 2.5, -10.125, 1e2]";
+        let options = FracturedJsonOptions {
+            max_inline_complexity: 0,
+            max_compact_array_complexity: 0,
+            max_table_row_complexity: 0,
+            number_list_alignment: NumberListAlignment::Normalize,
+            ..FracturedJsonOptions::default()
+        };
+        let result = format_jsonc(input, &options).unwrap();
+        assert_eq!(
+            result,
+            "[\n      1.000,\n      2.500,\n    -10.125,\n    100.000\n]"
+        );
+    }
+
+    #[test]
+    fn test_number_alignment_right() {
+        let input = "[1, 2.5, -10.125]";
+        let options = FracturedJsonOptions {
+            max_inline_complexity: 0,
+            number_list_alignment: NumberListAlignment::Right,
+            ..FracturedJsonOptions::default()
+        };
+        let result = format_jsonc(input, &options).unwrap();
+        assert_eq!(result, "[\n          1,\n        2.5,\n    -10.125\n]");
+    }
+
+    #[test]
+    fn test_integer_table_columns_right_aligned() {
+        let input = r#"[{"hp": 400, "x": 1.5}, {"hp": 80, "x": -12.25}]"#;
+        let options = FracturedJsonOptions {
+            max_inline_complexity: 0,
+            max_compact_array_complexity: 0,
+            max_table_row_complexity: 2,
+            ..FracturedJsonOptions::default()
+        };
+        // Only the integers are aligned when no alignment is set
+        let result = format_jsonc(input, &options).unwrap();
+        assert_eq!(
+            result,
+            "[\n    { \"hp\": 400, \"x\": 1.5    },\n    { \"hp\":  80, \"x\": -12.25 }\n]"
+        );
+    }
+
+    #[test]
+    fn test_number_alignment_in_compact_arrays() {
+        let input = "[1.5, -22, 3.125, 2e5, -10.25, 7, 1000, 4.5, 0.75]";
+        let options = FracturedJsonOptions {
+            max_total_line_length: 30,
+            max_inline_complexity: 0,
+            min_compact_array_row_items: 2,
+            number_list_alignment: NumberListAlignment::Decimal,
+            ..FracturedJsonOptions::default()
+        };
+        let result = format_jsonc(input, &options).unwrap();
+        assert_eq!(
+            result,
+            "[\n       1.5, -22,    3.125,\n       2e5, -10.25, 7,\n    1000,     4.5,  0.75\n]"
+        );
+    }
+
+    #[test]
+    fn test_number_alignment_in_tables() {
+        let input =
+            r#"[{"id": 7, "score": 1.5, "name": "a"}, {"id": -12, "score": 20, "name": "bcd"}]"#;
+        let options = FracturedJsonOptions {
+            max_inline_complexity: 0,
+            max_compact_array_complexity: 0,
+            max_table_row_complexity: 2,
+            number_list_alignment: NumberListAlignment::Decimal,
+            ..FracturedJsonOptions::default()
+        };
+        let result = format_jsonc(input, &options).unwrap();
+        assert_eq!(
+            result,
+            concat!(
+                "[\n",
+                "    { \"id\":   7, \"score\":  1.5, \"name\": \"a\"   },\n",
+                "    { \"id\": -12, \"score\": 20,   \"name\": \"bcd\" }\n",
+                "]"
+            )
+        );
+
+        // Short rows are padded to line up their closing brackets
+        let rows = "[[1, [2, 3]], [-4.25, {}], [5]]";
+        let options = FracturedJsonOptions {
+            max_table_row_complexity: 3,
+            ..options
+        };
+        let result = format_jsonc(rows, &options).unwrap();
+        assert_eq!(
+            result,
+            "[\n    [  1,    [ 2, 3 ] ],\n    [ -4.25, { }      ],\n    [  5              ]\n]"
+        );
+    }
+
+    #[test]
+    fn test_object_of_objects_table() {
+        let input = r#"{"alice": {"age": 31, "role": "admin"}, "bob": {"age": 7, "role": "x"}, "carolyn": {"age": 102, "role": "guest"}}"#;
+        let options = FracturedJsonOptions::default();
+        let result = format_jsonc(input, &options).unwrap();
+        assert_eq!(
+            result,
+            concat!(
+                "{\n",
+                "    \"alice\"  : { \"age\":  31, \"role\": \"admin\" },\n",
+                "    \"bob\"    : { \"age\":   7, \"role\": \"x\"     },\n",
+                "    \"carolyn\": { \"age\": 102, \"role\": \"guest\" }\n",
+                "}"
+            )
+        );
+
+        // Rows too long for a line leave only the names aligned
+        let narrow = FracturedJsonOptions {
+            max_total_line_length: 44,
+            ..options
+        };
+        let result = format_jsonc(input, &narrow).unwrap();
+        assert!(result.contains("\n    \"bob\"    : { \"age\": 7, \"role\": \"x\" },\n"));
+    }
+
+    #[test]
+    fn test_records_form_tables_by_default() {
+        let options = FracturedJsonOptions::default();
+        let units = r#"[
+            {"type": "turret", "hp": 400, "x": 47, "y": -4},
+            {"type": "assassin", "hp": 80, "x": 12, "y": 6},
+            {"type": "berserker", "hp": 150, "x": 0, "y": 0}
+        ]"#;
+        assert_eq!(
+            format_jsonc(units, &options).unwrap(),
+            concat!(
+                "[\n",
+                "    { \"type\": \"turret\",    \"hp\": 400, \"x\": 47, \"y\": -4 },\n",
+                "    { \"type\": \"assassin\",  \"hp\":  80, \"x\": 12, \"y\":  6 },\n",
+                "    { \"type\": \"berserker\", \"hp\": 150, \"x\":  0, \"y\":  0 }\n",
+                "]"
+            )
+        );
+
+        // Enough records for a compact array, which would fit one per row
+        let records: Vec<String> = (0..6)
+            .map(|i| {
+                format!(
+                    r#"{{"id": {}, "name": "row {}", "padding": "{}"}}"#,
+                    i * 50,
+                    i,
+                    "x".repeat(40)
+                )
+            })
+            .collect();
+        let output = format_jsonc(&format!("[{}]", records.join(", ")), &options).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 8);
+        assert!(
+            lines[1].starts_with(r#"    { "id":   0, "name": "row 0", "#),
+            "{output}"
+        );
+    }
+
+    #[test]
+    fn test_table_comma_placement() {
+        let input = r#"[["a", 1], ["bbb", 22]]"#;
+        let format_with = |table_comma_placement| {
+            let options = FracturedJsonOptions {
+                max_inline_complexity: 0,
+                max_compact_array_complexity: 0,
+                max_table_row_complexity: 2,
+                table_comma_placement,
+                ..FracturedJsonOptions::default()
+            };
+            format_jsonc(input, &options).unwrap()
+        };
+        assert_eq!(
+            format_with(TableCommaPlacement::BeforePadding),
+            "[\n    [ \"a\",    1 ],\n    [ \"bbb\", 22 ]\n]"
+        );
+        assert_eq!(
+            format_with(TableCommaPlacement::AfterPadding),
+            "[\n    [ \"a\"  ,  1 ],\n    [ \"bbb\", 22 ]\n]"
+        );
+        assert_eq!(
+            format_with(TableCommaPlacement::NextLine),
+            "[\n      [ \"a\",    1 ]\n    , [ \"bbb\", 22 ]\n]"
+        );
+        #[allow(deprecated)]
+        let end_of_line = TableCommaPlacement::EndOfLine;
+        assert_eq!(
+            "end-of-line".parse::<TableCommaPlacement>().unwrap(),
+            end_of_line
+        );
+        assert_eq!(
+            format_with(end_of_line),
+            format_with(TableCommaPlacement::BeforePadding)
+        );
+
+        // A single column keeps its commas at the ends of the rows
+        let options = FracturedJsonOptions {
+            max_inline_complexity: 0,
+            max_compact_array_complexity: 0,
+            max_table_row_complexity: 1,
+            table_comma_placement: TableCommaPlacement::NextLine,
+            ..FracturedJsonOptions::default()
+        };
+        assert_eq!(
+            format_jsonc("[1, 22]", &options).unwrap(),
+            "[\n     1,\n    22\n]"
+        );
+    }
+
     #[test]
     fn test_property_alignment() {
         let input = r#"{"name": "test", "value": 123, "description": "A test object"}"#;
@@ -169,6 +428,229 @@ mod tests {
         assert!(result.contains(",\n"));
     }
 
+    #[test]
+    fn test_final_newline() {
+        let input = r#"{"a": 1}"#;
+        let result = format_jsonc(input, &FracturedJsonOptions::default()).unwrap();
+        assert!(!result.ends_with('\n'));
+
+        let options = FracturedJsonOptions {
+            final_newline: true,
+            json_eol_style: EolStyle::Crlf,
+            ..FracturedJsonOptions::default()
+        };
+        let result = format_jsonc(input, &options).unwrap();
+        assert!(result.ends_with("}\r\n"));
+    }
+
+    #[test]
+    fn test_writer_output_matches_format() {
+        // Large enough to be flushed to the writer in several chunks
+        let rows: Vec<String> = (0..2000)
+            .map(|i| {
+                format!(
+                    r#"{{"id": {}, "name": "item {}", "tags": ["a", "b"]}}"#,
+                    i, i
+                )
+            })
+            .collect();
+        let input = format!("[{}]", rows.join(","));
+        let options = FracturedJsonOptions {
+            final_newline: true,
+            ..FracturedJsonOptions::default()
+        };
+        let expected = format_jsonc(&input, &options).unwrap();
+
+        let mut bytes = Vec::new();
+        format_jsonc_to_writer(&input, &options, &mut bytes).unwrap();
+        assert_eq!(String::from_utf8(bytes).unwrap(), expected);
+
+        let item = transform(&input, transform::parse(&input).unwrap());
+        let mut text = String::new();
+        format_to_fmt_writer(&item, &options, &mut text).unwrap();
+        assert_eq!(text, expected);
+    }
+
+    #[test]
+    fn test_writer_error_is_reported() {
+        struct Failing;
+        impl std::io::Write for Failing {
+            fn write(&mut self, _buf: &[u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::other("disk full"))
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let err = format_jsonc_to_writer("[1, 2]", &FracturedJsonOptions::default(), &mut Failing)
+            .unwrap_err();
+        assert!(err.to_string().contains("disk full"));
+    }
+
+    #[test]
+    fn test_expanded_nesting_indents_once() {
+        let input = r#"{"a": {"b": [1, {"c": 2}]}, "d": []}"#;
+        let options = FracturedJsonOptions {
+            max_inline_complexity: 0,
+            ..FracturedJsonOptions::default()
+        };
+        let expected = "{\n    \"a\": {\n        \"b\": [\n            1,\n            {\n                \"c\": 2\n            }\n        ]\n    },\n    \"d\": [ ]\n}";
+        assert_eq!(format_jsonc(input, &options).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_scalar_after_colon_not_indented() {
+        // Used to give `"a":     1`, the value indented again after the colon
+        let input = r#"{"a": 1, "b": {"c": "x"}}"#;
+        let options = FracturedJsonOptions {
+            max_inline_complexity: 0,
+            ..FracturedJsonOptions::default()
+        };
+        let output = format_jsonc(input, &options).unwrap();
+        assert!(output.contains("\n    \"a\": 1,\n"), "{output}");
+        assert!(output.contains("\n        \"c\": \"x\"\n"), "{output}");
+    }
+
+    #[test]
+    fn test_no_comma_padding_before_line_breaks() {
+        // comma_padding used to leave a space after each comma that ends a line
+        let input = r#"{"a": [1, 2], "b": true}"#;
+        let options = FracturedJsonOptions {
+            max_inline_complexity: 0,
+            comma_padding: true,
+            ..FracturedJsonOptions::default()
+        };
+        let output = format_jsonc(input, &options).unwrap();
+        assert!(
+            output.lines().all(|line| !line.ends_with(' ')),
+            "{output:?}"
+        );
+        assert!(output.contains("\n        1,\n"), "{output:?}");
+    }
+
+    #[test]
+    fn test_empty_containers_stay_inline() {
+        // Used to be expanded to `[` and `]` on lines of their own
+        let input = r#"{"d": [], "e": {}}"#;
+        let options = FracturedJsonOptions {
+            max_inline_complexity: 0,
+            max_compact_array_complexity: 0,
+            max_table_row_complexity: 0,
+            ..FracturedJsonOptions::default()
+        };
+        let expected = "{\n    \"d\": [ ],\n    \"e\": { }\n}";
+        assert_eq!(format_jsonc(input, &options).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_preset_json_like() {
+        let input = r#"{"name": "x", "list": [1, [2, 3], {}], "nested": {"ok": true}}"#;
+        let options = FracturedJsonOptions::preset(Preset::JsonLike);
+        let expected = "{\n  \"name\": \"x\",\n  \"list\": [\n    1,\n    [\n      2,\n      3\n    ],\n    {}\n  ],\n  \"nested\": {\n    \"ok\": true\n  }\n}";
+        assert_eq!(format_jsonc(input, &options).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_presets() {
+        let input = r#"{"a": [1, 2], "b": {"c": null}}"#;
+        let smart = FracturedJsonOptions::preset(Preset::Smart);
+        assert_eq!(
+            format_jsonc(input, &smart).unwrap(),
+            format_jsonc(input, &FracturedJsonOptions::default()).unwrap()
+        );
+
+        let pretty = format_jsonc(input, &FracturedJsonOptions::preset(Preset::Pretty)).unwrap();
+        assert_eq!(pretty.lines().count(), 9);
+
+        let compact = FracturedJsonOptions::preset(Preset::Compact);
+        assert_eq!(
+            format_jsonc(input, &compact).unwrap(),
+            r#"{"a": [1, 2], "b": {"c": null}}"#
+        );
+
+        // Fields set after the preset override it
+        let options = FracturedJsonOptions {
+            indent_spaces: 3,
+            ..FracturedJsonOptions::preset(Preset::Pretty)
+        };
+        assert!(format_jsonc(input, &options)
+            .unwrap()
+            .contains("\n   \"a\": [\n      1,"));
+        assert_eq!(
+            FracturedJsonOptions::preset(Preset::Wide).max_total_line_length,
+            200
+        );
+        assert_eq!("json-like".parse::<Preset>().unwrap(), Preset::JsonLike);
+    }
+
+    #[test]
+    fn test_sort_keys() {
+        let input = r#"{"b10": 1, "b2": 2, "a": {"z": 0, "y": 0}, "B1": 3}"#;
+        let sorted = |sort_keys| {
+            let options = FracturedJsonOptions {
+                sort_keys,
+                ..FracturedJsonOptions::preset(Preset::Compact)
+            };
+            minify(input, &options).unwrap()
+        };
+        assert_eq!(
+            sorted(SortKeys::Alphabetical),
+            r#"{"B1":3,"a":{"y":0,"z":0},"b10":1,"b2":2}"#
+        );
+        assert_eq!(
+            sorted(SortKeys::Natural),
+            r#"{"B1":3,"a":{"y":0,"z":0},"b2":2,"b10":1}"#
+        );
+        assert_eq!(
+            sorted(SortKeys::Custom(vec!["b2".into(), "a".into()])),
+            r#"{"b2":2,"a":{"z":0,"y":0},"b10":1,"B1":3}"#
+        );
+        assert_eq!(
+            sorted(SortKeys::None),
+            minify(input, &FracturedJsonOptions::default()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_sorted_keys_keep_their_comments() {
+        let input = "{\n    // about b\n    \"b\": 1,\n    \"a\": /* mid */ 2\n}";
+        let options = FracturedJsonOptions {
+            sort_keys: SortKeys::Alphabetical,
+            max_table_row_complexity: 0,
+            ..FracturedJsonOptions::default()
+        };
+        let result = format_jsonc(input, &options).unwrap();
+        assert!(result.contains("// about b\n    \"b\": 1"));
+        assert!(result.find("\"a\"") < result.find("/* mid */"));
+        assert!(result.find("/* mid */") < result.find("// about b"));
+    }
+
+    #[test]
+    fn test_duplicate_key_diagnostics() {
+        let input = r#"{"a": 1, "b": 2, "a": 3}"#;
+        let options = FracturedJsonOptions {
+            duplicate_key_policy: DuplicateKeyPolicy::Warn,
+            ..FracturedJsonOptions::default()
+        };
+        let (result, diagnostics) = format_jsonc_with_diagnostics(input, &options).unwrap();
+        assert_eq!(
+            result,
+            format_jsonc(input, &FracturedJsonOptions::default()).unwrap()
+        );
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        assert_eq!(diagnostics[0].position.unwrap().column, 18);
+
+        let options = FracturedJsonOptions {
+            duplicate_key_policy: DuplicateKeyPolicy::KeepLast,
+            ..FracturedJsonOptions::default()
+        };
+        let (result, diagnostics) = format_jsonc_with_diagnostics(input, &options).unwrap();
+        assert_eq!(result, "{ \"a\": 3, \"b\": 2 }");
+        assert!(diagnostics.is_empty());
+    }
+
     #[test]
     fn test_indentation_spaces() {
         let input = r#"{"a": {"b": 1}}"#;
@@ -1,177 +1,792 @@
 use clap::Parser;
+use fractured_json_rs::computed::ItemRef;
+use fractured_json_rs::diagnostics::line_length_diagnostics;
 use fractured_json_rs::{
-    format_jsonc, CommentPolicy, EolStyle, FracturedJsonOptions, NumberListAlignment,
+    canonicalize_item_with_diagnostics, config, explain, format, format_computed,
+    format_computed_to_writer, format_reader_low_memory, minify_item, parse_document,
+    CommentPolicy, ConfigFile, Diagnostic, DuplicateKeyPolicy, EolStyle, FracturedJsonError,
+    FracturedJsonOptions, JsonItem, JsonItemType, NumberListAlignment, Preset, SortKeys,
     TableCommaPlacement,
 };
-use std::fs;
-use std::io::{self, Read, Write};
-use std::path::PathBuf;
+use similar::TextDiff;
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::{self, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+
+const UTF8_BOM: char = '\u{feff}';
+
+/// Exit code for `--check` when at least one input is not formatted.
+const EXIT_NEEDS_FORMATTING: i32 = 1;
+/// Exit code when an input could not be read or parsed.
+const EXIT_ERROR: i32 = 2;
 
 #[derive(Parser, Debug)]
 #[command(name = "fractured-json")]
 #[command(about = "A human-friendly JSONC formatter", long_about = None)]
 struct Cli {
+    /// Files to format; reads stdin when none are given
+    files: Vec<PathBuf>,
+
     #[arg(short, long)]
     input: Option<PathBuf>,
 
-    #[arg(short, long)]
+    #[arg(short, long, conflicts_with = "write")]
     output: Option<PathBuf>,
 
+    /// Rewrite each input file in place
+    #[arg(short, long, conflicts_with = "check")]
+    write: bool,
+
     #[arg(short = 'j', long = "json")]
     is_json: bool,
 
-    #[arg(long, default_value = "120")]
-    max_line_length: usize,
+    /// Use this config file instead of searching for one
+    #[arg(long, conflicts_with = "no_config")]
+    config: Option<PathBuf>,
 
-    #[arg(long, default_value = "4")]
-    indent: String,
+    /// Ignore .fracturedjson.toml / .fracturedjson.jsonc files
+    #[arg(long)]
+    no_config: bool,
 
-    #[arg(long, default_value = "preserve")]
-    comment_policy: String,
+    /// Print the resolved options as TOML and exit
+    #[arg(long)]
+    print_config: bool,
 
-    #[arg(long, default_value = "1")]
-    max_inline_complexity: u32,
+    /// What to do with repeated keys: keep, error, keep-first, keep-last or
+    /// warn
+    #[arg(long)]
+    duplicate_key_policy: Option<DuplicateKeyPolicy>,
 
-    #[arg(long, default_value = "2")]
-    max_compact_array_complexity: u32,
+    /// Fail an input, writing nothing for it, if it has any warnings
+    #[arg(long)]
+    deny_warnings: bool,
 
-    #[arg(long, default_value = "1")]
-    max_table_row_complexity: u32,
+    /// Start from a named preset; the config file and other flags override it
+    #[arg(long)]
+    preset: Option<Preset>,
 
-    #[arg(long, default_value = "40")]
-    max_prop_name_padding: usize,
+    #[arg(long)]
+    max_line_length: Option<usize>,
 
-    #[arg(long, default_value = "false")]
+    /// Indentation width, or "tab"
+    #[arg(long, value_parser = parse_indent)]
+    indent: Option<Indent>,
+
+    #[arg(long)]
+    comment_policy: Option<CommentPolicy>,
+
+    #[arg(long)]
+    max_inline_complexity: Option<u32>,
+
+    #[arg(long)]
+    max_compact_array_complexity: Option<u32>,
+
+    #[arg(long)]
+    max_table_row_complexity: Option<u32>,
+
+    #[arg(long)]
+    max_prop_name_padding: Option<usize>,
+
+    /// Put colons right after property names, before their padding
+    #[arg(long, overrides_with = "no_colon_before_prop_name_padding")]
     colon_before_prop_name_padding: bool,
 
-    #[arg(long, default_value = "end-of-line")]
-    table_comma_placement: String,
+    #[arg(long, overrides_with = "colon_before_prop_name_padding")]
+    no_colon_before_prop_name_padding: bool,
 
-    #[arg(long, default_value = "4")]
-    min_compact_array_row_items: usize,
+    /// Where commas go in tables: before-padding, after-padding or next-line
+    #[arg(long)]
+    table_comma_placement: Option<TableCommaPlacement>,
 
-    #[arg(long, default_value = "0")]
-    always_expand_depth: i32,
+    #[arg(long)]
+    min_compact_array_row_items: Option<usize>,
 
-    #[arg(long, default_value = "false")]
+    /// Order object properties: none, alphabetical or natural
+    #[arg(long, conflicts_with = "key_order")]
+    sort_keys: Option<SortKeys>,
+
+    /// Write these keys first, in this order, then the rest in source order
+    #[arg(long, value_delimiter = ',', value_name = "KEYS")]
+    key_order: Option<Vec<String>>,
+
+    #[arg(long)]
+    always_expand_depth: Option<i32>,
+
+    /// Pad the inside of brackets that hold containers
+    #[arg(long, overrides_with = "no_nested_bracket_padding")]
+    nested_bracket_padding: bool,
+
+    #[arg(long, overrides_with = "nested_bracket_padding")]
     no_nested_bracket_padding: bool,
 
-    #[arg(long, default_value = "false")]
+    /// Pad the inside of brackets that hold only values
+    #[arg(long, overrides_with = "no_simple_bracket_padding")]
+    simple_bracket_padding: bool,
+
+    #[arg(long, overrides_with = "simple_bracket_padding")]
     no_simple_bracket_padding: bool,
 
-    #[arg(long, default_value = "false")]
+    /// Put a space after colons
+    #[arg(long, overrides_with = "no_colon_padding")]
+    colon_padding: bool,
+
+    #[arg(long, overrides_with = "colon_padding")]
     no_colon_padding: bool,
 
-    #[arg(long, default_value = "false")]
+    /// Put a space after commas
+    #[arg(long, overrides_with = "no_comma_padding")]
+    comma_padding: bool,
+
+    #[arg(long, overrides_with = "comma_padding")]
     no_comma_padding: bool,
 
-    #[arg(long, default_value = "false")]
+    /// Put a space between values and their comments
+    #[arg(long, overrides_with = "no_comment_padding")]
+    comment_padding: bool,
+
+    #[arg(long, overrides_with = "comment_padding")]
     no_comment_padding: bool,
 
-    #[arg(long, default_value = "none")]
-    number_list_alignment: String,
+    #[arg(long)]
+    number_list_alignment: Option<NumberListAlignment>,
 
-    #[arg(long, default_value = "")]
-    prefix_string: String,
+    #[arg(long)]
+    prefix_string: Option<String>,
 
-    #[arg(long, default_value = "false")]
+    /// Keep blank lines from the input
+    #[arg(long, overrides_with = "no_preserve_blank_lines")]
+    preserve_blank_lines: bool,
+
+    #[arg(long, overrides_with = "preserve_blank_lines")]
     no_preserve_blank_lines: bool,
 
-    #[arg(long, default_value = "false")]
+    /// Add trailing commas
+    #[arg(long, overrides_with = "no_allow_trailing_commas")]
     allow_trailing_commas: bool,
 
+    #[arg(long, overrides_with = "allow_trailing_commas")]
+    no_allow_trailing_commas: bool,
+
     #[arg(long, default_value = "false")]
     check: bool,
+
+    /// With --check, print a unified diff of the changes formatting would make
+    #[arg(long, requires = "check")]
+    diff: bool,
+
+    /// With --check, how to report results
+    #[arg(long, default_value = "text", value_parser = ["text", "json"], requires = "check")]
+    report: String,
+
+    /// End the output with a line ending
+    #[arg(long, overrides_with = "no_final_newline")]
+    final_newline: bool,
+
+    #[arg(long, overrides_with = "final_newline")]
+    no_final_newline: bool,
+
+    /// Stream the input instead of loading it whole, for very large documents
+    #[arg(long, conflicts_with_all = ["write", "check"])]
+    low_memory: bool,
+
+    /// Print the layout chosen for each container, and why, instead of the
+    /// formatted output
+    #[arg(long, conflicts_with_all = ["write", "check", "low_memory"])]
+    explain: bool,
+
+    /// Write everything on one line with no whitespace; comments are kept as
+    /// block comments unless --comment-policy remove
+    #[arg(long, conflicts_with_all = ["low_memory", "explain"])]
+    minify: bool,
+
+    /// Write RFC 8785 canonical JSON (sorted keys, no comments or whitespace)
+    #[arg(long, conflicts_with_all = ["low_memory", "explain", "minify"])]
+    canonical: bool,
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let cli = Cli::parse();
 
+    let paths: Vec<PathBuf> = cli.input.iter().chain(cli.files.iter()).cloned().collect();
+    let mut configs = ConfigCache::default();
+
+    if cli.print_config {
+        let options = resolve_options(&cli, &mut configs, paths.first().map(PathBuf::as_path))?;
+        print!("{}", config::to_toml_string(&options));
+        return Ok(());
+    }
+
+    if cli.write {
+        if paths.is_empty() {
+            return Err("--write requires at least one input file".into());
+        }
+        let mut failed = false;
+        for path in &paths {
+            if let Err(err) = write_in_place(&cli, &mut configs, path) {
+                eprintln!("{}: {}", path.display(), err);
+                failed = true;
+            }
+        }
+        if failed {
+            std::process::exit(EXIT_ERROR);
+        }
+        return Ok(());
+    }
+
+    if cli.check {
+        std::process::exit(run_check(&cli, &mut configs, &paths));
+    }
+
+    if paths.len() > 1 && cli.output.is_some() {
+        return Err("--output takes a single input; use --write for several files".into());
+    }
+
+    if cli.low_memory {
+        if paths.len() > 1 {
+            return Err("--low-memory takes a single input".into());
+        }
+        let options = resolve_options(&cli, &mut configs, paths.first().map(PathBuf::as_path))?;
+        let reader: Box<dyn Read> = match paths.first() {
+            Some(path) => Box::new(fs::File::open(path)?),
+            None => Box::new(io::stdin().lock()),
+        };
+        match &cli.output {
+            Some(output_path) => {
+                let mut file = fs::File::create(output_path)?;
+                format_reader_low_memory(reader, &mut file, &options)?;
+            }
+            None => format_reader_low_memory(reader, &mut io::stdout().lock(), &options)?,
+        }
+        return Ok(());
+    }
+
+    if paths.len() > 1 {
+        let mut stdout = io::BufWriter::new(io::stdout().lock());
+        for path in &paths {
+            let options = resolve_options(&cli, &mut configs, Some(path))?;
+            let input = fs::read_to_string(path)?;
+            write_source(
+                &cli,
+                &path.display().to_string(),
+                &input,
+                &options,
+                &mut stdout,
+            )?;
+        }
+        stdout.flush()?;
+        return Ok(());
+    }
+
     let mut input = String::new();
-    if let Some(input_path) = &cli.input {
+    if let Some(input_path) = paths.first() {
         input = fs::read_to_string(input_path)?;
     } else {
         io::stdin().read_to_string(&mut input)?;
     }
 
-    let mut options = FracturedJsonOptions {
-        max_total_line_length: cli.max_line_length,
-        max_inline_complexity: cli.max_inline_complexity,
-        max_compact_array_complexity: cli.max_compact_array_complexity,
-        max_table_row_complexity: cli.max_table_row_complexity,
-        max_prop_name_padding: cli.max_prop_name_padding,
-        colon_before_prop_name_padding: cli.colon_before_prop_name_padding,
-        min_compact_array_row_items: cli.min_compact_array_row_items,
-        always_expand_depth: cli.always_expand_depth,
-        nested_bracket_padding: !cli.no_nested_bracket_padding,
-        simple_bracket_padding: !cli.no_simple_bracket_padding,
-        colon_padding: !cli.no_colon_padding,
-        comma_padding: !cli.no_comma_padding,
-        comment_padding: !cli.no_comment_padding,
-        prefix_string: cli.prefix_string,
-        allow_trailing_commas: cli.allow_trailing_commas,
-        ..FracturedJsonOptions::default()
-    };
+    let options = resolve_options(&cli, &mut configs, paths.first().map(PathBuf::as_path))?;
+    let name = paths
+        .first()
+        .map_or("<stdin>".into(), |p| p.display().to_string());
+
+    if cli.explain {
+        let body = input.strip_prefix(UTF8_BOM).unwrap_or(&input);
+        let mut report = String::new();
+        for explanation in explain(body, &options)? {
+            report.push_str(&explanation.to_string());
+            report.push('\n');
+        }
+        match &cli.output {
+            Some(output_path) => fs::write(output_path, report)?,
+            None => io::stdout().lock().write_all(report.as_bytes())?,
+        }
+        return Ok(());
+    }
 
-    if cli.indent == "tab" {
-        options.use_tab_to_indent = true;
-    } else if let Ok(spaces) = cli.indent.parse::<usize>() {
-        options.indent_spaces = spaces;
-        options.use_tab_to_indent = false;
+    if let Some(output_path) = &cli.output {
+        fs::write(output_path, format_source(&cli, &name, &input, &options)?)?;
+    } else {
+        let mut stdout = io::BufWriter::new(io::stdout().lock());
+        write_source(&cli, &name, &input, &options, &mut stdout)?;
+        stdout.flush()?;
     }
+    Ok(())
+}
 
-    if cli.comment_policy == "remove" {
-        options.comment_policy = CommentPolicy::Remove;
+/// Outcome of checking a single input.
+enum CheckOutcome {
+    Formatted,
+    NeedsFormatting { diff: Option<String> },
+    Error(String),
+}
+
+/// Checks every input (or stdin) and returns the process exit code.
+///
+/// An input passes only if formatting it reproduces its bytes exactly.
+fn run_check(cli: &Cli, configs: &mut ConfigCache, paths: &[PathBuf]) -> i32 {
+    let sources: Vec<Option<&Path>> = if paths.is_empty() {
+        vec![None]
     } else {
-        options.comment_policy = CommentPolicy::Preserve;
+        paths.iter().map(|p| Some(p.as_path())).collect()
+    };
+
+    let mut results = Vec::with_capacity(sources.len());
+    for path in sources {
+        let name = path.map_or("<stdin>".into(), |p| p.display().to_string());
+        let result = resolve_options(cli, configs, path).and_then(|options| {
+            let input = match path {
+                Some(path) => fs::read_to_string(path)?,
+                None => {
+                    let mut input = String::new();
+                    io::stdin().read_to_string(&mut input)?;
+                    input
+                }
+            };
+            let output = format_source(cli, &name, &input, &options)?;
+            Ok((input, output))
+        });
+
+        let outcome = match result {
+            Ok((input, output)) if input == output => CheckOutcome::Formatted,
+            Ok((input, output)) => CheckOutcome::NeedsFormatting {
+                diff: cli.diff.then(|| unified_diff(&name, &input, &output)),
+            },
+            Err(err) => CheckOutcome::Error(err.to_string()),
+        };
+        results.push((name, outcome));
     }
 
-    match cli.table_comma_placement.as_str() {
-        "next-line" => {
-            options.table_comma_placement = TableCommaPlacement::NextLine;
+    if cli.report == "json" {
+        let report = check_report_json(&results);
+        let _ = io::stdout().write_all(report.as_bytes());
+    } else {
+        let color = io::stdout().is_terminal();
+        for (name, outcome) in &results {
+            match outcome {
+                CheckOutcome::Formatted => {}
+                CheckOutcome::NeedsFormatting { diff } => {
+                    eprintln!("{}: needs formatting", name);
+                    if let Some(diff) = diff {
+                        print_diff(diff, color);
+                    }
+                }
+                CheckOutcome::Error(message) => eprintln!("{}: {}", name, message),
+            }
         }
-        _ => {
-            options.table_comma_placement = TableCommaPlacement::EndOfLine;
+    }
+
+    if results
+        .iter()
+        .any(|(_, outcome)| matches!(outcome, CheckOutcome::Error(_)))
+    {
+        EXIT_ERROR
+    } else if results
+        .iter()
+        .any(|(_, outcome)| matches!(outcome, CheckOutcome::NeedsFormatting { .. }))
+    {
+        EXIT_NEEDS_FORMATTING
+    } else {
+        0
+    }
+}
+
+/// Renders a unified diff from `input` to `output`.
+fn unified_diff(name: &str, input: &str, output: &str) -> String {
+    TextDiff::from_lines(input, output)
+        .unified_diff()
+        .context_radius(3)
+        .header(name, &format!("{} (formatted)", name))
+        .to_string()
+}
+
+/// Prints a rendered unified diff, colored for terminals.
+fn print_diff(diff: &str, color: bool) {
+    let mut stdout = io::stdout().lock();
+    for line in diff.split_inclusive('\n') {
+        let style = if !color {
+            None
+        } else if line.starts_with("---") || line.starts_with("+++") {
+            Some("\x1b[1m")
+        } else if line.starts_with("@@") {
+            Some("\x1b[36m")
+        } else if line.starts_with('-') {
+            Some("\x1b[31m")
+        } else if line.starts_with('+') {
+            Some("\x1b[32m")
+        } else {
+            None
+        };
+        let _ = match style {
+            Some(style) => write!(
+                stdout,
+                "{}{}\x1b[0m{}",
+                style,
+                line.trim_end_matches('\n'),
+                if line.ends_with('\n') { "\n" } else { "" }
+            ),
+            None => stdout.write_all(line.as_bytes()),
+        };
+    }
+}
+
+/// Builds the `--report json` document with the formatter itself.
+fn check_report_json(results: &[(String, CheckOutcome)]) -> String {
+    fn string_item(name: &str, value: &str) -> JsonItem<'static> {
+        JsonItem::new(JsonItemType::String)
+            .with_value(value.to_string())
+            .with_name(name.to_string())
+    }
+
+    fn count_item(name: &str, count: usize) -> JsonItem<'static> {
+        JsonItem::new(JsonItemType::Number)
+            .with_value(count.to_string())
+            .with_name(name.to_string())
+    }
+
+    let mut files = JsonItem::new(JsonItemType::Array).with_name("files".to_string());
+    let (mut unformatted, mut errors) = (0, 0);
+    for (name, outcome) in results {
+        let mut entry = JsonItem::new(JsonItemType::Object);
+        entry.children.push(string_item("path", name));
+        match outcome {
+            CheckOutcome::Formatted => {
+                entry.children.push(string_item("status", "formatted"));
+            }
+            CheckOutcome::NeedsFormatting { diff } => {
+                unformatted += 1;
+                entry
+                    .children
+                    .push(string_item("status", "needs_formatting"));
+                if let Some(diff) = diff {
+                    entry.children.push(string_item("diff", diff));
+                }
+            }
+            CheckOutcome::Error(message) => {
+                errors += 1;
+                entry.children.push(string_item("status", "error"));
+                entry.children.push(string_item("message", message));
+            }
         }
+        files.children.push(entry);
     }
 
-    match cli.number_list_alignment.as_str() {
-        "left" => {
-            options.number_list_alignment = NumberListAlignment::Left;
+    let mut summary = JsonItem::new(JsonItemType::Object).with_name("summary".to_string());
+    summary.children.push(count_item("checked", results.len()));
+    summary
+        .children
+        .push(count_item("needs_formatting", unformatted));
+    summary.children.push(count_item("errors", errors));
+
+    let mut report = JsonItem::new(JsonItemType::Object);
+    report.children.push(files);
+    report.children.push(summary);
+
+    let options = FracturedJsonOptions {
+        final_newline: true,
+        ..FracturedJsonOptions::default()
+    };
+    format(&report, &options)
+}
+
+/// Resolves the options for `path` (or stdin when `None`): defaults, then
+/// the nearest config file and its matching overrides, then CLI flags.
+fn resolve_options(
+    cli: &Cli,
+    configs: &mut ConfigCache,
+    path: Option<&Path>,
+) -> fractured_json_rs::Result<FracturedJsonOptions> {
+    let mut options = cli
+        .preset
+        .map_or_else(FracturedJsonOptions::default, FracturedJsonOptions::preset);
+    let path = path.map(std::path::absolute).transpose()?;
+
+    if !cli.no_config {
+        if let Some(config) = configs.find(cli, path.as_deref())? {
+            config.apply(&mut options, path.as_deref())?;
         }
-        "decimal" => {
-            options.number_list_alignment = NumberListAlignment::Decimal;
+    }
+
+    apply_cli_flags(cli, &mut options);
+    Ok(options)
+}
+
+/// Config files already loaded, so that formatting many files reads and
+/// parses each config once rather than once per file.
+#[derive(Default)]
+struct ConfigCache {
+    /// Keyed by the `--config` path, or else by the directory the search for
+    /// a config file started from.
+    configs: HashMap<PathBuf, Option<ConfigFile>>,
+}
+
+impl ConfigCache {
+    /// The config file that applies to `path` (or stdin when `None`).
+    fn find(
+        &mut self,
+        cli: &Cli,
+        path: Option<&Path>,
+    ) -> fractured_json_rs::Result<Option<&ConfigFile>> {
+        let key = match (&cli.config, path.and_then(Path::parent)) {
+            (Some(config_path), _) => config_path.clone(),
+            (None, Some(dir)) => dir.to_path_buf(),
+            (None, None) => std::env::current_dir()?,
+        };
+        if !self.configs.contains_key(&key) {
+            let config = match &cli.config {
+                Some(_) => Some(ConfigFile::load(&key)?),
+                None => ConfigFile::discover(&key)?,
+            };
+            self.configs.insert(key.clone(), config);
         }
-        _ => {
-            options.number_list_alignment = NumberListAlignment::None;
+        Ok(self.configs[&key].as_ref())
+    }
+}
+
+/// Indentation given with `--indent`.
+#[derive(Debug, Clone, Copy)]
+enum Indent {
+    Tab,
+    Spaces(usize),
+}
+
+fn parse_indent(value: &str) -> Result<Indent, String> {
+    if value == "tab" {
+        return Ok(Indent::Tab);
+    }
+    value
+        .parse()
+        .map(Indent::Spaces)
+        .map_err(|_| format!("expected a number of spaces or \"tab\", got `{value}`"))
+}
+
+/// The setting given by a `--flag`/`--no-flag` pair, if either was given;
+/// clap keeps only the later of the two.
+fn flag(on: bool, off: bool) -> Option<bool> {
+    match (on, off) {
+        (true, _) => Some(true),
+        (_, true) => Some(false),
+        _ => None,
+    }
+}
+
+/// Applies the flags given on the command line over `options`.
+fn apply_cli_flags(cli: &Cli, options: &mut FracturedJsonOptions) {
+    if let Some(max_line_length) = cli.max_line_length {
+        options.max_total_line_length = max_line_length;
+    }
+    if let Some(complexity) = cli.max_inline_complexity {
+        options.max_inline_complexity = complexity;
+    }
+    if let Some(complexity) = cli.max_compact_array_complexity {
+        options.max_compact_array_complexity = complexity;
+    }
+    if let Some(complexity) = cli.max_table_row_complexity {
+        options.max_table_row_complexity = complexity;
+    }
+    if let Some(padding) = cli.max_prop_name_padding {
+        options.max_prop_name_padding = padding;
+    }
+    if let Some(before) = flag(
+        cli.colon_before_prop_name_padding,
+        cli.no_colon_before_prop_name_padding,
+    ) {
+        options.colon_before_prop_name_padding = before;
+    }
+    if let Some(placement) = cli.table_comma_placement {
+        options.table_comma_placement = placement;
+    }
+    if let Some(items) = cli.min_compact_array_row_items {
+        options.min_compact_array_row_items = items;
+    }
+    if let Some(policy) = cli.duplicate_key_policy {
+        options.duplicate_key_policy = policy;
+    }
+    if let Some(sort_keys) = &cli.sort_keys {
+        options.sort_keys = sort_keys.clone();
+    }
+    if let Some(keys) = &cli.key_order {
+        options.sort_keys = SortKeys::Custom(keys.clone());
+    }
+    if let Some(depth) = cli.always_expand_depth {
+        options.always_expand_depth = depth;
+    }
+    if let Some(padding) = flag(cli.nested_bracket_padding, cli.no_nested_bracket_padding) {
+        options.nested_bracket_padding = padding;
+    }
+    if let Some(padding) = flag(cli.simple_bracket_padding, cli.no_simple_bracket_padding) {
+        options.simple_bracket_padding = padding;
+    }
+    if let Some(padding) = flag(cli.colon_padding, cli.no_colon_padding) {
+        options.colon_padding = padding;
+    }
+    if let Some(padding) = flag(cli.comma_padding, cli.no_comma_padding) {
+        options.comma_padding = padding;
+    }
+    if let Some(padding) = flag(cli.comment_padding, cli.no_comment_padding) {
+        options.comment_padding = padding;
+    }
+    if let Some(alignment) = cli.number_list_alignment {
+        options.number_list_alignment = alignment;
+    }
+    if let Some(prefix) = &cli.prefix_string {
+        options.prefix_string = prefix.clone();
+    }
+    if let Some(preserve) = flag(cli.preserve_blank_lines, cli.no_preserve_blank_lines) {
+        options.preserve_blank_lines = preserve;
+    }
+    if let Some(allow) = flag(cli.allow_trailing_commas, cli.no_allow_trailing_commas) {
+        options.allow_trailing_commas = allow;
+    }
+    if let Some(final_newline) = flag(cli.final_newline, cli.no_final_newline) {
+        options.final_newline = final_newline;
+    }
+
+    match cli.indent {
+        Some(Indent::Tab) => options.use_tab_to_indent = true,
+        Some(Indent::Spaces(spaces)) => {
+            options.indent_spaces = spaces;
+            options.use_tab_to_indent = false;
         }
+        None => {}
     }
 
-    if cli.no_preserve_blank_lines {
-        options.preserve_blank_lines = false;
+    if let Some(policy) = cli.comment_policy {
+        options.comment_policy = policy;
     }
 
     if cli.is_json {
         options.json_eol_style = EolStyle::Lf;
         options.comment_policy = CommentPolicy::Remove;
     }
+}
+
+/// Prints `diagnostics` on stderr under `name`, failing with
+/// `--deny-warnings` if there are any.
+fn report_diagnostics(
+    cli: &Cli,
+    name: &str,
+    mut diagnostics: Vec<Diagnostic>,
+) -> fractured_json_rs::Result<()> {
+    diagnostics.sort_by_key(|d| d.position.map(|p| (p.line, p.column)));
+    for diagnostic in &diagnostics {
+        eprintln!("{}:{}", name, diagnostic);
+    }
+    if cli.deny_warnings && !diagnostics.is_empty() {
+        return Err(FracturedJsonError::FormattingError(format!(
+            "{} warning(s) denied by --deny-warnings",
+            diagnostics.len()
+        )));
+    }
+    Ok(())
+}
 
-    let output = format_jsonc(&input, &options)?;
+/// Formats (or with `--minify`, minifies) `input`, keeping a leading UTF-8
+/// BOM if the source had one. Canonical output never has a BOM.
+fn format_source(
+    cli: &Cli,
+    name: &str,
+    input: &str,
+    options: &FracturedJsonOptions,
+) -> fractured_json_rs::Result<String> {
+    let body = input.strip_prefix(UTF8_BOM).unwrap_or(input);
+    if cli.canonical {
+        // Canonical output has no comments, so none can be lost
+        let options = FracturedJsonOptions {
+            comment_policy: CommentPolicy::Remove,
+            ..options.clone()
+        };
+        let (item, mut diagnostics) = parse_document(body, &options)?;
+        let (output, precision) = canonicalize_item_with_diagnostics(&item)?;
+        diagnostics.extend(precision);
+        report_diagnostics(cli, name, diagnostics)?;
+        return Ok(output);
+    }
 
-    if cli.check {
-        if input.trim() == output.trim() {
-            println!("Formatted correctly");
-            Ok(())
-        } else {
-            eprintln!("File needs formatting");
-            std::process::exit(1);
-        }
+    let (item, mut diagnostics) = parse_document(body, options)?;
+    let mut output = String::new();
+    if input.starts_with(UTF8_BOM) {
+        output.push(UTF8_BOM);
+    }
+    if cli.minify {
+        report_diagnostics(cli, name, diagnostics)?;
+        output.push_str(&minify_item(&item, options));
     } else {
-        if let Some(output_path) = &cli.output {
-            fs::write(output_path, output)?;
-        } else {
-            io::stdout().write_all(output.as_bytes())?;
-        }
-        Ok(())
+        let computed = ItemRef::from_root(&item, options);
+        diagnostics.extend(line_length_diagnostics(&computed, options));
+        report_diagnostics(cli, name, diagnostics)?;
+        output.push_str(&format_computed(&computed, options));
+    }
+    Ok(output)
+}
+
+/// Streams the formatted `input` to `writer`, keeping a leading UTF-8 BOM.
+fn write_source<W: Write>(
+    cli: &Cli,
+    name: &str,
+    input: &str,
+    options: &FracturedJsonOptions,
+    writer: &mut W,
+) -> fractured_json_rs::Result<()> {
+    if cli.minify || cli.canonical {
+        writer.write_all(format_source(cli, name, input, options)?.as_bytes())?;
+        return Ok(());
+    }
+    let body = input.strip_prefix(UTF8_BOM).unwrap_or(input);
+    let (item, mut diagnostics) = parse_document(body, options)?;
+    let computed = ItemRef::from_root(&item, options);
+    diagnostics.extend(line_length_diagnostics(&computed, options));
+    report_diagnostics(cli, name, diagnostics)?;
+    if input.starts_with(UTF8_BOM) {
+        write!(writer, "{}", UTF8_BOM)?;
+    }
+    format_computed_to_writer(&computed, options, writer)?;
+    Ok(())
+}
+
+/// Formats the file at `path` and replaces it atomically, leaving it untouched
+/// when the formatted output is identical to what is already on disk.
+fn write_in_place(
+    cli: &Cli,
+    configs: &mut ConfigCache,
+    path: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let options = resolve_options(cli, configs, Some(path))?;
+    let input = fs::read_to_string(path)?;
+    let output = format_source(cli, &path.display().to_string(), &input, &options)?;
+    if output != input {
+        write_atomically(path, output.as_bytes())?;
+    }
+    Ok(())
+}
+
+/// Writes `contents` to a temporary file next to `path` and renames it over
+/// the original, so readers never observe a partially written file.
+fn write_atomically(path: &Path, contents: &[u8]) -> io::Result<()> {
+    // Replace the symlink target rather than the link itself
+    let path = fs::canonicalize(path)?;
+    let permissions = fs::metadata(&path)?.permissions();
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "not a file path"))?;
+    let mut tmp_name = std::ffi::OsString::from(".");
+    tmp_name.push(file_name);
+    tmp_name.push(format!(".{}.tmp", std::process::id()));
+    let tmp_path = path.with_file_name(tmp_name);
+
+    let result = (|| {
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&tmp_path)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+        fs::set_permissions(&tmp_path, permissions)?;
+        fs::rename(&tmp_path, &path)
+    })();
+
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path);
     }
+    result
 }
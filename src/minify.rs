@@ -0,0 +1,160 @@
+//! Minified output: the whole document on one line with no whitespace.
+
+use crate::error::Result;
+use crate::formatter::escape_string;
+use crate::options::{CommentPolicy, FracturedJsonOptions};
+use crate::transform;
+use crate::types::{JsonItem, JsonItemType};
+
+/// Parses `input` and writes it with all whitespace removed.
+///
+/// With [`CommentPolicy::Preserve`] comments are kept as block comments so
+/// the output stays on one line; with [`CommentPolicy::Remove`] they are
+/// dropped and the output is plain JSON.
+pub fn minify(input: &str, options: &FracturedJsonOptions) -> Result<String> {
+    let (item, _) = transform::parse_document(input, options)?;
+    Ok(minify_item(&item, options))
+}
+
+/// Writes `item` with all whitespace removed. See [`minify`].
+pub fn minify_item(item: &JsonItem, options: &FracturedJsonOptions) -> String {
+    let mut buffer = String::new();
+    write_item(item, options, &mut buffer);
+    if options.final_newline {
+        buffer.push_str(options.eol_string());
+    }
+    buffer
+}
+
+fn write_item(item: &JsonItem, options: &FracturedJsonOptions, buffer: &mut String) {
+    let keep_comments = options.comment_policy == CommentPolicy::Preserve;
+    match item.item_type {
+        JsonItemType::Array | JsonItemType::Object => {
+            let is_object = item.item_type == JsonItemType::Object;
+            buffer.push(if is_object { '{' } else { '[' });
+            let mut children: Vec<&JsonItem> = item.children.iter().collect();
+            if is_object {
+                options.sort_keys.sort(&mut children, |c| &c.name);
+            }
+            let mut first = true;
+            for child in children {
+                match child.item_type {
+                    JsonItemType::LineComment | JsonItemType::BlockComment => {
+                        if keep_comments {
+                            write_comment(&child.value, buffer);
+                        }
+                        continue;
+                    }
+                    JsonItemType::BlankLine => continue,
+                    _ => {}
+                }
+                if !first {
+                    buffer.push(',');
+                }
+                first = false;
+                write_child(child, is_object, options, buffer);
+            }
+            buffer.push(if is_object { '}' } else { ']' });
+        }
+        JsonItemType::String => {
+            buffer.push('"');
+            buffer.push_str(&escape_string(&item.value));
+            buffer.push('"');
+        }
+        JsonItemType::Number => buffer.push_str(&item.value),
+        JsonItemType::True => buffer.push_str("true"),
+        JsonItemType::False => buffer.push_str("false"),
+        JsonItemType::Null => buffer.push_str("null"),
+        JsonItemType::LineComment | JsonItemType::BlockComment => {
+            if keep_comments {
+                write_comment(&item.value, buffer);
+            }
+        }
+        JsonItemType::BlankLine => {}
+    }
+}
+
+fn write_child(
+    item: &JsonItem,
+    is_property: bool,
+    options: &FracturedJsonOptions,
+    buffer: &mut String,
+) {
+    let keep_comments = options.comment_policy == CommentPolicy::Preserve;
+    if keep_comments {
+        if let Some(comment) = &item.prefix_comment {
+            write_comment(comment, buffer);
+        }
+    }
+    if is_property {
+        buffer.push('"');
+        buffer.push_str(&escape_string(&item.name));
+        buffer.push_str("\":");
+    }
+    if keep_comments {
+        if let Some(comment) = &item.middle_comment {
+            write_comment(comment, buffer);
+        }
+    }
+    write_item(item, options, buffer);
+    if keep_comments {
+        if let Some(comment) = &item.postfix_comment {
+            write_comment(comment, buffer);
+        }
+    }
+}
+
+/// Writes `comment` as a block comment on one line. Line comments would
+/// swallow everything after them, so they are rewritten as block comments.
+fn write_comment(comment: &str, buffer: &mut String) {
+    let body = match comment.strip_prefix("//") {
+        Some(line) => line.trim(),
+        None => comment
+            .strip_prefix("/*")
+            .and_then(|c| c.strip_suffix("*/"))
+            .unwrap_or(comment)
+            .trim(),
+    };
+    buffer.push_str("/*");
+    let mut words = body.split_whitespace();
+    if let Some(word) = words.next() {
+        buffer.push_str(&word.replace("*/", "* /"));
+        for word in words {
+            buffer.push(' ');
+            buffer.push_str(&word.replace("*/", "* /"));
+        }
+    }
+    buffer.push_str("*/");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_minify_drops_whitespace() {
+        let input = "{\n  \"a b\": [1, 2.50, \"x\\\"y\"],\n  \"c\": {\"d\": null, \"e\": true}\n}";
+        let options = FracturedJsonOptions::default();
+        assert_eq!(
+            minify(input, &options).unwrap(),
+            r#"{"a b":[1,2.50,"x\"y"],"c":{"d":null,"e":true}}"#
+        );
+    }
+
+    #[test]
+    fn test_minify_comments() {
+        let input = "{\n  // lead */ here\n  \"a\": /* mid */ 1,\n  \"b\": [\n    1\n    /* multi\n       line */\n  ]\n}";
+        let options = FracturedJsonOptions::default();
+        assert_eq!(
+            minify(input, &options).unwrap(),
+            r#"{/*lead * / here*/"a":/*mid*/1,"b":[1/*multi line*/]}"#
+        );
+
+        let options = FracturedJsonOptions {
+            comment_policy: CommentPolicy::Remove,
+            final_newline: true,
+            ..FracturedJsonOptions::default()
+        };
+        assert_eq!(minify(input, &options).unwrap(), "{\"a\":1,\"b\":[1]}\n");
+    }
+}
@@ -0,0 +1,300 @@
+//! How the numbers of a list or table column are written so they line up.
+
+use crate::options::NumberListAlignment;
+use std::borrow::Cow;
+
+/// Most significant digits a number is given when normalizing, the most a
+/// double always holds exactly. Columns that would need more are left as
+/// they are.
+pub const MAX_NORMALIZED_DIGITS: usize = 15;
+
+/// The plan for writing one list or table column of numbers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NumberColumn {
+    /// With [`NumberListAlignment::Normalize`], the decimal places every
+    /// number is rewritten with; `None` when numbers are written as they are.
+    pub decimals: Option<usize>,
+    /// Widest part before the decimal point or exponent, sign included.
+    pub integer_width: usize,
+    /// Widest part from the decimal point or exponent on.
+    pub fraction_width: usize,
+    /// Width every number in the column is padded to.
+    pub width: usize,
+    /// How numbers are padded to `width`.
+    pub alignment: NumberListAlignment,
+}
+
+impl NumberColumn {
+    /// Plans a column of `values`, or returns `None` when `alignment` leaves
+    /// numbers alone.
+    pub(crate) fn plan<'v>(
+        values: impl Iterator<Item = &'v str> + Clone,
+        alignment: NumberListAlignment,
+    ) -> Option<NumberColumn> {
+        let decimals = match alignment {
+            NumberListAlignment::None => return None,
+            NumberListAlignment::Normalize => normalized_decimals(values.clone()),
+            NumberListAlignment::Left
+            | NumberListAlignment::Right
+            | NumberListAlignment::Decimal => None,
+        };
+        let column = NumberColumn {
+            decimals,
+            integer_width: 0,
+            fraction_width: 0,
+            width: 0,
+            alignment,
+        };
+        Some(column.measure(values))
+    }
+
+    /// A column written the same way as this one, such as the decimal places
+    /// chosen for a whole list, but sized for just `values`.
+    pub(crate) fn measure<'v>(&self, values: impl Iterator<Item = &'v str>) -> NumberColumn {
+        let mut column = NumberColumn {
+            decimals: self.decimals,
+            integer_width: 0,
+            fraction_width: 0,
+            width: 0,
+            alignment: self.alignment,
+        };
+        let mut longest = 0;
+        for value in values {
+            let text = column.text(value);
+            let (integer, fraction) = split_at_point(&text);
+            column.integer_width = column.integer_width.max(integer.len());
+            column.fraction_width = column.fraction_width.max(fraction.len());
+            longest = longest.max(text.len());
+        }
+        column.width = match column.alignment {
+            NumberListAlignment::None | NumberListAlignment::Left | NumberListAlignment::Right => {
+                longest
+            }
+            NumberListAlignment::Decimal | NumberListAlignment::Normalize => {
+                column.integer_width + column.fraction_width
+            }
+        };
+        column
+    }
+
+    /// `value` rewritten for this column, without padding.
+    pub(crate) fn text<'v>(&self, value: &'v str) -> Cow<'v, str> {
+        match self.decimals.and_then(|d| normalize(value, d)) {
+            Some(normalized) => Cow::Owned(normalized),
+            None => Cow::Borrowed(value),
+        }
+    }
+
+    /// `value` rewritten and padded to `width` per `alignment`. Decimal
+    /// alignment lines up the decimal points, or the exponent of a number
+    /// without one, padding integer parts on the left and fractions on the
+    /// right. Normalized numbers all have the same decimal places, so come
+    /// out right-aligned.
+    pub(crate) fn pad(&self, value: &str) -> String {
+        let text = self.text(value);
+        match self.alignment {
+            NumberListAlignment::None => text.into_owned(),
+            NumberListAlignment::Left => format!("{:<width$}", text, width = self.width),
+            NumberListAlignment::Right => format!("{:>width$}", text, width = self.width),
+            NumberListAlignment::Decimal | NumberListAlignment::Normalize => {
+                let (integer, fraction) = split_at_point(&text);
+                format!(
+                    "{:>integer_width$}{:<fraction_width$}",
+                    integer,
+                    fraction,
+                    integer_width = self.integer_width,
+                    fraction_width = self.fraction_width
+                )
+            }
+        }
+    }
+}
+
+/// Whether `text` is a number without a fraction or exponent.
+pub(crate) fn is_integer(text: &str) -> bool {
+    let digits = text.strip_prefix('-').unwrap_or(text);
+    !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit())
+}
+
+/// Splits a number before its decimal point, or before the exponent when
+/// it has none, so `-2.5e3` gives `-2` and `.5e3`, and `7E2` gives `7` and
+/// `E2`.
+fn split_at_point(text: &str) -> (&str, &str) {
+    text.split_at(text.find(['.', 'e', 'E']).unwrap_or(text.len()))
+}
+
+/// A number in plain notation: its sign, integer digits without leading
+/// zeros (but at least `0`), and fraction digits as written.
+struct Plain {
+    negative: bool,
+    integer: String,
+    fraction: String,
+}
+
+impl Plain {
+    /// Reads a JSON number, moving the decimal point per any exponent.
+    fn parse(text: &str) -> Option<Plain> {
+        let (negative, unsigned) = match text.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, text),
+        };
+        let (mantissa, exponent) = match unsigned.find(['e', 'E']) {
+            Some(at) => (&unsigned[..at], unsigned[at + 1..].parse::<i64>().ok()?),
+            None => (unsigned, 0),
+        };
+        let (integer, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        let all_digits = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+        if integer.is_empty() || !all_digits(integer) || !all_digits(fraction) {
+            return None;
+        }
+
+        let digits = format!("{}{}", integer, fraction);
+        // Digits before the point once the exponent is applied; a huge
+        // exponent could never fit under the digit cap anyway
+        let point = integer.len() as i64 + exponent;
+        if point.unsigned_abs() as usize > digits.len() + MAX_NORMALIZED_DIGITS {
+            return None;
+        }
+        let (integer, fraction) = if point <= 0 {
+            (
+                String::new(),
+                "0".repeat(point.unsigned_abs() as usize) + &digits,
+            )
+        } else if point as usize >= digits.len() {
+            (
+                digits.clone() + &"0".repeat(point as usize - digits.len()),
+                String::new(),
+            )
+        } else {
+            let (integer, fraction) = digits.split_at(point as usize);
+            (integer.to_string(), fraction.to_string())
+        };
+        let integer = integer.trim_start_matches('0');
+        Some(Plain {
+            negative,
+            integer: if integer.is_empty() { "0" } else { integer }.to_string(),
+            fraction,
+        })
+    }
+
+    /// Significant digits once the fraction is padded to `decimals`.
+    fn significant_digits(&self, decimals: usize) -> usize {
+        let integer = if self.integer == "0" {
+            0
+        } else {
+            self.integer.len()
+        };
+        if integer > 0 {
+            return integer + decimals;
+        }
+        let leading_zeros = self.fraction.bytes().take_while(|&b| b == b'0').count();
+        decimals.saturating_sub(leading_zeros)
+    }
+}
+
+/// Decimal places that every number in `values` can be rewritten with
+/// without changing its value: the most any of them has. `None` when a value
+/// isn't a plain JSON number or would need more than
+/// [`MAX_NORMALIZED_DIGITS`].
+fn normalized_decimals<'v>(values: impl Iterator<Item = &'v str> + Clone) -> Option<usize> {
+    let mut decimals = 0;
+    for value in values.clone() {
+        decimals = decimals.max(Plain::parse(value)?.fraction.len());
+    }
+    for value in values {
+        // Numbers that come out as written don't count against the cap
+        let plain = Plain::parse(value)?;
+        if normalize(value, decimals).as_deref() != Some(value)
+            && plain.significant_digits(decimals) > MAX_NORMALIZED_DIGITS
+        {
+            return None;
+        }
+    }
+    Some(decimals)
+}
+
+/// `text` in plain notation with exactly `decimals` decimal places, or
+/// `None` if it has more than that or isn't a number.
+fn normalize(text: &str, decimals: usize) -> Option<String> {
+    let plain = Plain::parse(text)?;
+    if plain.fraction.len() > decimals {
+        return None;
+    }
+    let mut out = String::with_capacity(plain.integer.len() + decimals + 2);
+    if plain.negative {
+        out.push('-');
+    }
+    out.push_str(&plain.integer);
+    if decimals > 0 {
+        out.push('.');
+        out.push_str(&plain.fraction);
+        out.extend(std::iter::repeat_n('0', decimals - plain.fraction.len()));
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn padded(values: &[&str], alignment: NumberListAlignment) -> Vec<String> {
+        let column = NumberColumn::plan(values.iter().copied(), alignment).unwrap();
+        values.iter().map(|v| column.pad(v)).collect()
+    }
+
+    fn normalized(values: &[&str]) -> Vec<String> {
+        padded(values, NumberListAlignment::Normalize)
+    }
+
+    #[test]
+    fn test_decimal_alignment() {
+        assert_eq!(
+            padded(&["1", "-22.5", "3.125", "-4"], NumberListAlignment::Decimal),
+            ["  1    ", "-22.5  ", "  3.125", " -4    "]
+        );
+        // Exponents line up with the decimal point
+        assert_eq!(
+            padded(&["2e5", "10.5", "-1.5E-3"], NumberListAlignment::Decimal),
+            [" 2e5   ", "10.5   ", "-1.5E-3"]
+        );
+        assert_eq!(
+            padded(&["1", "-22.5"], NumberListAlignment::Left),
+            ["1    ", "-22.5"]
+        );
+        assert_eq!(
+            padded(&["1", "-22.5"], NumberListAlignment::Right),
+            ["    1", "-22.5"]
+        );
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(
+            normalized(&["1", "2.5", "-10.125", "0"]),
+            ["  1.000", "  2.500", "-10.125", "  0.000"]
+        );
+        // Exponents are written out
+        assert_eq!(
+            normalized(&["1e3", "2.5E-2", "-0.5e1"]),
+            ["1000.000", "   0.025", "  -5.000"]
+        );
+        assert_eq!(normalized(&["007", "1.50"]), ["7.00", "1.50"]);
+        assert_eq!(
+            normalized(&["12345678901234567890", "1"]),
+            ["12345678901234567890", "                   1"]
+        );
+    }
+
+    #[test]
+    fn test_normalize_falls_back_to_decimal_alignment() {
+        // Padding 1 to 20 places would need more digits than a double has
+        assert_eq!(
+            normalized(&["1", "0.00000000000000000001"]),
+            ["1                     ", "0.00000000000000000001"]
+        );
+        assert_eq!(
+            normalized(&["123456789.5", "0.1234567"]),
+            ["123456789.5      ", "        0.1234567"]
+        );
+    }
+}
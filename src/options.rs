@@ -1,4 +1,11 @@
+use crate::error::FracturedJsonError;
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
 pub enum EolStyle {
     Lf,
     Crlf,
@@ -6,25 +13,211 @@ pub enum EolStyle {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
 pub enum TableCommaPlacement {
-    EndOfLine,
+    /// Commas right after each value, with the padding after them.
+    BeforePadding,
+    /// Commas after the padding, so they line up in columns.
+    AfterPadding,
+    /// Rows start with the comma separating them from the row before;
+    /// commas within a row go before the padding. Tables of a single
+    /// column, which would read like a broken list, get `BeforePadding`.
     NextLine,
+    /// The old name of the default; the same as `BeforePadding`.
+    #[deprecated(note = "use `TableCommaPlacement::BeforePadding`")]
+    EndOfLine,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
 pub enum NumberListAlignment {
+    /// Write numbers as they are, except in table columns of integers,
+    /// which are right-aligned.
     None,
     Left,
+    /// Pad numbers on the left to the longest, so their last digits line up.
+    Right,
     Decimal,
+    /// Rewrite the numbers of each list or column to the same number of
+    /// decimal places, where that keeps their values, and right-align them.
+    Normalize,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
 pub enum CommentPolicy {
     Preserve,
     Remove,
 }
 
+/// What to do when an object has several properties with the same name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum DuplicateKeyPolicy {
+    /// Keep every property.
+    Keep,
+    /// Fail with [`FracturedJsonError::DuplicateKey`].
+    Error,
+    /// Keep the first property with each name.
+    KeepFirst,
+    /// Keep the last property with each name, where the first one was.
+    KeepLast,
+    /// Keep every property and report each duplicate as a warning.
+    Warn,
+}
+
+/// Order in which object properties are written. Comments move with the
+/// property they are attached to.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum SortKeys {
+    /// Source order.
+    #[default]
+    None,
+    /// By Unicode code point.
+    Alphabetical,
+    /// Like `Alphabetical`, but runs of digits compare by their numeric
+    /// value, so `item2` comes before `item10`.
+    Natural,
+    /// The listed keys first, in list order, then the rest in source order.
+    Custom(Vec<String>),
+}
+
+impl SortKeys {
+    /// Accepted spellings of the variants without data.
+    pub const NAMES: &'static [&'static str] = &["none", "alphabetical", "natural"];
+
+    /// Stably reorders `items`, a list of properties named by `name`.
+    pub fn sort<T>(&self, items: &mut [T], name: impl Fn(&T) -> &str) {
+        match self {
+            SortKeys::None => {}
+            SortKeys::Alphabetical => items.sort_by(|a, b| name(a).cmp(name(b))),
+            SortKeys::Natural => items.sort_by(|a, b| natural_cmp(name(a), name(b))),
+            SortKeys::Custom(order) => items.sort_by_key(|item| {
+                let key = name(item);
+                order.iter().position(|k| k == key).unwrap_or(order.len())
+            }),
+        }
+    }
+}
+
+/// Compares digit runs by value and everything else by code point. Ties,
+/// such as `a01` and `a1`, fall back to plain comparison.
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut x, mut y) = (a, b);
+    while !x.is_empty() && !y.is_empty() {
+        let (x_run, x_rest) = split_run(x);
+        let (y_run, y_rest) = split_run(y);
+        let is_digits = |run: &str| run.starts_with(|c: char| c.is_ascii_digit());
+        let ordering = if is_digits(x_run) && is_digits(y_run) {
+            let x_num = x_run.trim_start_matches('0');
+            let y_num = y_run.trim_start_matches('0');
+            x_num.len().cmp(&y_num.len()).then_with(|| x_num.cmp(y_num))
+        } else {
+            x_run.cmp(y_run)
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+        (x, y) = (x_rest, y_rest);
+    }
+    x.len().cmp(&y.len()).then_with(|| a.cmp(b))
+}
+
+/// Splits off the leading run of ASCII digits or of other characters.
+fn split_run(s: &str) -> (&str, &str) {
+    let digits = s.starts_with(|c: char| c.is_ascii_digit());
+    let end = s
+        .find(|c: char| c.is_ascii_digit() != digits)
+        .unwrap_or(s.len());
+    s.split_at(end)
+}
+
+impl FromStr for SortKeys {
+    type Err = FracturedJsonError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(SortKeys::None),
+            "alphabetical" => Ok(SortKeys::Alphabetical),
+            "natural" => Ok(SortKeys::Natural),
+            _ => Err(FracturedJsonError::InvalidOption(format!(
+                "unknown SortKeys `{}` (expected one of: {}, or a list of keys)",
+                s,
+                SortKeys::NAMES.join(", ")
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for SortKeys {
+    /// Writes the name, or the comma-separated keys for `Custom`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SortKeys::None => f.write_str("none"),
+            SortKeys::Alphabetical => f.write_str("alphabetical"),
+            SortKeys::Natural => f.write_str("natural"),
+            SortKeys::Custom(keys) => f.write_str(&keys.join(",")),
+        }
+    }
+}
+
+/// Serialized like the config file: a name, or the list of keys for `Custom`.
+#[cfg(feature = "serde")]
+impl serde::Serialize for SortKeys {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            SortKeys::Custom(keys) => keys.serialize(serializer),
+            named => serializer.collect_str(named),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for SortKeys {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(serde::Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Name(String),
+            Keys(Vec<String>),
+        }
+        match Repr::deserialize(deserializer)? {
+            Repr::Name(name) => name.parse().map_err(serde::de::Error::custom),
+            Repr::Keys(keys) => Ok(SortKeys::Custom(keys)),
+        }
+    }
+}
+
+/// A named starting point for [`FracturedJsonOptions`], see
+/// [`FracturedJsonOptions::preset`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Preset {
+    /// Packs as much onto each line as fits, with 2-space indents and no
+    /// bracket padding.
+    Compact,
+    /// One item per line; only empty containers stay on one line.
+    Pretty,
+    /// The default: inline, compact-array and table layouts where they fit.
+    Smart,
+    /// One item per line with 2-space indents, like
+    /// `JSON.stringify(value, null, 2)`.
+    JsonLike,
+    /// `Smart` tuned for 200-column screens.
+    Wide,
+}
+
+/// Formatter settings.
+///
+/// With the `serde` feature, options (de)serialize with kebab-case field and
+/// variant names, the same keys used by `.fracturedjson.toml`; missing fields
+/// take their default values.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case", default))]
 pub struct FracturedJsonOptions {
     pub json_eol_style: EolStyle,
     pub max_total_line_length: usize,
@@ -35,6 +228,8 @@ pub struct FracturedJsonOptions {
     pub colon_before_prop_name_padding: bool,
     pub table_comma_placement: TableCommaPlacement,
     pub min_compact_array_row_items: usize,
+    pub sort_keys: SortKeys,
+    pub duplicate_key_policy: DuplicateKeyPolicy,
     pub always_expand_depth: i32,
     pub nested_bracket_padding: bool,
     pub simple_bracket_padding: bool,
@@ -48,6 +243,8 @@ pub struct FracturedJsonOptions {
     pub comment_policy: CommentPolicy,
     pub preserve_blank_lines: bool,
     pub allow_trailing_commas: bool,
+    /// End the output with a line ending.
+    pub final_newline: bool,
 }
 
 impl Default for FracturedJsonOptions {
@@ -57,11 +254,13 @@ impl Default for FracturedJsonOptions {
             max_total_line_length: 120,
             max_inline_complexity: 1,
             max_compact_array_complexity: 2,
-            max_table_row_complexity: 1,
+            max_table_row_complexity: 2,
             max_prop_name_padding: 40,
             colon_before_prop_name_padding: false,
-            table_comma_placement: TableCommaPlacement::EndOfLine,
+            table_comma_placement: TableCommaPlacement::BeforePadding,
             min_compact_array_row_items: 4,
+            sort_keys: SortKeys::None,
+            duplicate_key_policy: DuplicateKeyPolicy::Keep,
             always_expand_depth: 0,
             nested_bracket_padding: true,
             simple_bracket_padding: true,
@@ -75,11 +274,48 @@ impl Default for FracturedJsonOptions {
             comment_policy: CommentPolicy::Preserve,
             preserve_blank_lines: true,
             allow_trailing_commas: false,
+            final_newline: false,
         }
     }
 }
 
 impl FracturedJsonOptions {
+    /// Options for `preset`. Set fields afterwards to override it.
+    pub fn preset(preset: Preset) -> Self {
+        let smart = FracturedJsonOptions::default();
+        let pretty = FracturedJsonOptions {
+            max_inline_complexity: 0,
+            max_compact_array_complexity: 0,
+            max_table_row_complexity: 0,
+            ..smart.clone()
+        };
+        match preset {
+            Preset::Smart => smart,
+            Preset::Pretty => pretty,
+            Preset::JsonLike => FracturedJsonOptions {
+                indent_spaces: 2,
+                simple_bracket_padding: false,
+                ..pretty
+            },
+            Preset::Compact => FracturedJsonOptions {
+                max_inline_complexity: 3,
+                max_compact_array_complexity: 3,
+                max_table_row_complexity: 3,
+                nested_bracket_padding: false,
+                simple_bracket_padding: false,
+                indent_spaces: 2,
+                ..smart
+            },
+            Preset::Wide => FracturedJsonOptions {
+                max_total_line_length: 200,
+                max_inline_complexity: 2,
+                max_compact_array_complexity: 3,
+                max_table_row_complexity: 2,
+                ..smart
+            },
+        }
+    }
+
     pub fn eol_string(&self) -> &'static str {
         match self.json_eol_style {
             EolStyle::Lf => "\n",
@@ -88,3 +324,83 @@ impl FracturedJsonOptions {
         }
     }
 }
+
+/// Implements kebab-case `FromStr`/`Display` for an option enum, as used by
+/// the CLI and configuration files.
+macro_rules! option_names {
+    ($ty:ident { $($variant:ident => $name:literal),+ $(,)? }) => {
+        impl $ty {
+            /// Accepted spellings, in declaration order.
+            pub const NAMES: &'static [&'static str] = &[$($name),+];
+        }
+
+        // Deprecated variants keep their names
+        #[allow(deprecated)]
+        impl FromStr for $ty {
+            type Err = FracturedJsonError;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                match s {
+                    $($name => Ok($ty::$variant),)+
+                    _ => Err(FracturedJsonError::InvalidOption(format!(
+                        "unknown {} `{}` (expected one of: {})",
+                        stringify!($ty),
+                        s,
+                        $ty::NAMES.join(", ")
+                    ))),
+                }
+            }
+        }
+
+        #[allow(deprecated)]
+        impl fmt::Display for $ty {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(match self {
+                    $($ty::$variant => $name,)+
+                })
+            }
+        }
+    };
+}
+
+option_names!(EolStyle {
+    Lf => "lf",
+    Crlf => "crlf",
+    Default => "default",
+});
+
+option_names!(TableCommaPlacement {
+    BeforePadding => "before-padding",
+    AfterPadding => "after-padding",
+    NextLine => "next-line",
+    EndOfLine => "end-of-line",
+});
+
+option_names!(NumberListAlignment {
+    None => "none",
+    Left => "left",
+    Right => "right",
+    Decimal => "decimal",
+    Normalize => "normalize",
+});
+
+option_names!(Preset {
+    Compact => "compact",
+    Pretty => "pretty",
+    Smart => "smart",
+    JsonLike => "json-like",
+    Wide => "wide",
+});
+
+option_names!(DuplicateKeyPolicy {
+    Keep => "keep",
+    Error => "error",
+    KeepFirst => "keep-first",
+    KeepLast => "keep-last",
+    Warn => "warn",
+});
+
+option_names!(CommentPolicy {
+    Preserve => "preserve",
+    Remove => "remove",
+});
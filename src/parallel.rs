@@ -0,0 +1,36 @@
+//! Data parallelism over sibling subtrees, behind the `rayon` feature.
+//!
+//! Without the feature every helper runs sequentially. Results are always
+//! collected in input order, so output does not depend on scheduling.
+
+/// Containers with fewer children than this are handled sequentially; the
+/// per-task overhead outweighs the work for small ones.
+#[cfg(feature = "rayon")]
+pub(crate) const PARALLEL_THRESHOLD: usize = 64;
+
+/// Maps `f` over `items`, in parallel when the `rayon` feature is enabled and
+/// there are at least [`PARALLEL_THRESHOLD`] of them.
+#[cfg(feature = "rayon")]
+pub(crate) fn map<'a, T, U, F>(items: &'a [T], f: F) -> Vec<U>
+where
+    T: Sync,
+    U: Send,
+    F: Fn(&'a T) -> U + Sync + Send,
+{
+    use rayon::prelude::*;
+
+    if items.len() >= PARALLEL_THRESHOLD {
+        items.par_iter().map(f).collect()
+    } else {
+        items.iter().map(f).collect()
+    }
+}
+
+/// Maps `f` over `items`.
+#[cfg(not(feature = "rayon"))]
+pub(crate) fn map<'a, T, U, F>(items: &'a [T], f: F) -> Vec<U>
+where
+    F: Fn(&'a T) -> U,
+{
+    items.iter().map(f).collect()
+}
@@ -0,0 +1,678 @@
+//! A `serde::Serializer` that builds a [`JsonItem`] tree.
+//!
+//! Data is laid out the way `serde_json` would serialize it: options become
+//! `null` or their contents, enums are externally tagged, and map keys that
+//! are numbers, booleans or chars are converted to strings.
+
+use crate::error::{FracturedJsonError, Result};
+use crate::formatter::{format, format_to_writer};
+use crate::options::FracturedJsonOptions;
+use crate::types::{JsonItem, JsonItemType};
+use serde::ser::{self, Impossible, Serialize};
+use std::fmt::Display;
+use std::io;
+
+/// Serializes `value` into a `JsonItem` tree.
+pub fn to_item<T: Serialize + ?Sized>(value: &T) -> Result<JsonItem<'static>> {
+    value.serialize(Serializer)
+}
+
+/// Serializes `value` and formats it, like `serde_json::to_string_pretty`.
+pub fn to_string_fractured<T: Serialize + ?Sized>(
+    value: &T,
+    options: &FracturedJsonOptions,
+) -> Result<String> {
+    Ok(format(&to_item(value)?, options))
+}
+
+/// Serializes `value` and writes it formatted, like `serde_json::to_writer_pretty`.
+pub fn to_writer_fractured<W: io::Write, T: Serialize + ?Sized>(
+    mut writer: W,
+    value: &T,
+    options: &FracturedJsonOptions,
+) -> Result<()> {
+    format_to_writer(&to_item(value)?, options, &mut writer)?;
+    Ok(())
+}
+
+impl ser::Error for FracturedJsonError {
+    fn custom<T: Display>(msg: T) -> Self {
+        FracturedJsonError::Serialize(msg.to_string())
+    }
+}
+
+/// Serializer whose output is a `JsonItem`.
+pub struct Serializer;
+
+fn scalar(item_type: JsonItemType, value: String) -> JsonItem<'static> {
+    JsonItem::new(item_type).with_value(value)
+}
+
+fn null() -> JsonItem<'static> {
+    scalar(JsonItemType::Null, "null".to_string())
+}
+
+fn float(value: f64) -> JsonItem<'static> {
+    if value.is_finite() {
+        scalar(
+            JsonItemType::Number,
+            ryu::Buffer::new().format_finite(value).to_string(),
+        )
+    } else {
+        null()
+    }
+}
+
+/// Wraps `value` as `{ "variant": value }`, serde's externally tagged form.
+fn tagged(variant: &str, value: JsonItem<'static>) -> JsonItem<'static> {
+    let mut item = JsonItem::new(JsonItemType::Object);
+    item.children.push(value.with_name(variant.to_string()));
+    item
+}
+
+impl ser::Serializer for Serializer {
+    type Ok = JsonItem<'static>;
+    type Error = FracturedJsonError;
+
+    type SerializeSeq = SerializeVec;
+    type SerializeTuple = SerializeVec;
+    type SerializeTupleStruct = SerializeVec;
+    type SerializeTupleVariant = SerializeVec;
+    type SerializeMap = SerializeMap;
+    type SerializeStruct = SerializeMap;
+    type SerializeStructVariant = SerializeMap;
+
+    fn serialize_bool(self, v: bool) -> Result<JsonItem<'static>> {
+        Ok(if v {
+            scalar(JsonItemType::True, "true".to_string())
+        } else {
+            scalar(JsonItemType::False, "false".to_string())
+        })
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<JsonItem<'static>> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<JsonItem<'static>> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<JsonItem<'static>> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<JsonItem<'static>> {
+        Ok(scalar(JsonItemType::Number, v.to_string()))
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<JsonItem<'static>> {
+        Ok(scalar(JsonItemType::Number, v.to_string()))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<JsonItem<'static>> {
+        self.serialize_u64(v.into())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<JsonItem<'static>> {
+        self.serialize_u64(v.into())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<JsonItem<'static>> {
+        self.serialize_u64(v.into())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<JsonItem<'static>> {
+        Ok(scalar(JsonItemType::Number, v.to_string()))
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<JsonItem<'static>> {
+        Ok(scalar(JsonItemType::Number, v.to_string()))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<JsonItem<'static>> {
+        if v.is_finite() {
+            Ok(scalar(
+                JsonItemType::Number,
+                ryu::Buffer::new().format_finite(v).to_string(),
+            ))
+        } else {
+            Ok(null())
+        }
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<JsonItem<'static>> {
+        Ok(float(v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<JsonItem<'static>> {
+        Ok(scalar(JsonItemType::String, v.to_string()))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<JsonItem<'static>> {
+        Ok(scalar(JsonItemType::String, v.to_string()))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<JsonItem<'static>> {
+        let mut item = JsonItem::new(JsonItemType::Array);
+        item.children = v
+            .iter()
+            .map(|b| scalar(JsonItemType::Number, b.to_string()))
+            .collect();
+        Ok(item)
+    }
+
+    fn serialize_none(self) -> Result<JsonItem<'static>> {
+        Ok(null())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<JsonItem<'static>> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<JsonItem<'static>> {
+        Ok(null())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<JsonItem<'static>> {
+        Ok(null())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<JsonItem<'static>> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<JsonItem<'static>> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<JsonItem<'static>> {
+        Ok(tagged(variant, value.serialize(self)?))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SerializeVec> {
+        Ok(SerializeVec {
+            variant: None,
+            items: Vec::with_capacity(len.unwrap_or(0)),
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SerializeVec> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<SerializeVec> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SerializeVec> {
+        Ok(SerializeVec {
+            variant: Some(variant),
+            items: Vec::with_capacity(len),
+        })
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<SerializeMap> {
+        Ok(SerializeMap {
+            variant: None,
+            items: Vec::with_capacity(len.unwrap_or(0)),
+            pending_key: None,
+        })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<SerializeMap> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SerializeMap> {
+        Ok(SerializeMap {
+            variant: Some(variant),
+            items: Vec::with_capacity(len),
+            pending_key: None,
+        })
+    }
+}
+
+/// Collects the elements of sequences, tuples and tuple variants.
+pub struct SerializeVec {
+    variant: Option<&'static str>,
+    items: Vec<JsonItem<'static>>,
+}
+
+impl SerializeVec {
+    fn finish(self) -> JsonItem<'static> {
+        let mut item = JsonItem::new(JsonItemType::Array);
+        item.children = self.items;
+        match self.variant {
+            Some(variant) => tagged(variant, item),
+            None => item,
+        }
+    }
+}
+
+impl ser::SerializeSeq for SerializeVec {
+    type Ok = JsonItem<'static>;
+    type Error = FracturedJsonError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.items.push(value.serialize(Serializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<JsonItem<'static>> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeTuple for SerializeVec {
+    type Ok = JsonItem<'static>;
+    type Error = FracturedJsonError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<JsonItem<'static>> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeTupleStruct for SerializeVec {
+    type Ok = JsonItem<'static>;
+    type Error = FracturedJsonError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<JsonItem<'static>> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeTupleVariant for SerializeVec {
+    type Ok = JsonItem<'static>;
+    type Error = FracturedJsonError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<JsonItem<'static>> {
+        Ok(self.finish())
+    }
+}
+
+/// Collects the properties of maps, structs and struct variants.
+pub struct SerializeMap {
+    variant: Option<&'static str>,
+    items: Vec<JsonItem<'static>>,
+    pending_key: Option<String>,
+}
+
+impl SerializeMap {
+    fn finish(self) -> JsonItem<'static> {
+        let mut item = JsonItem::new(JsonItemType::Object);
+        item.children = self.items;
+        match self.variant {
+            Some(variant) => tagged(variant, item),
+            None => item,
+        }
+    }
+}
+
+impl ser::SerializeMap for SerializeMap {
+    type Ok = JsonItem<'static>;
+    type Error = FracturedJsonError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<()> {
+        self.pending_key = Some(key.serialize(MapKeySerializer)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        let key = self.pending_key.take().ok_or_else(|| {
+            FracturedJsonError::Serialize("serialize_value called before serialize_key".into())
+        })?;
+        self.items.push(value.serialize(Serializer)?.with_name(key));
+        Ok(())
+    }
+
+    fn end(self) -> Result<JsonItem<'static>> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeStruct for SerializeMap {
+    type Ok = JsonItem<'static>;
+    type Error = FracturedJsonError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.items
+            .push(value.serialize(Serializer)?.with_name(key.to_string()));
+        Ok(())
+    }
+
+    fn end(self) -> Result<JsonItem<'static>> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeStructVariant for SerializeMap {
+    type Ok = JsonItem<'static>;
+    type Error = FracturedJsonError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        ser::SerializeStruct::serialize_field(self, key, value)
+    }
+
+    fn end(self) -> Result<JsonItem<'static>> {
+        Ok(self.finish())
+    }
+}
+
+/// Turns map keys into property names, accepting the same key types as
+/// `serde_json`.
+struct MapKeySerializer;
+
+fn key_must_be_a_string() -> FracturedJsonError {
+    FracturedJsonError::Serialize("key must be a string".to_string())
+}
+
+impl ser::Serializer for MapKeySerializer {
+    type Ok = String;
+    type Error = FracturedJsonError;
+
+    type SerializeSeq = Impossible<String, FracturedJsonError>;
+    type SerializeTuple = Impossible<String, FracturedJsonError>;
+    type SerializeTupleStruct = Impossible<String, FracturedJsonError>;
+    type SerializeTupleVariant = Impossible<String, FracturedJsonError>;
+    type SerializeMap = Impossible<String, FracturedJsonError>;
+    type SerializeStruct = Impossible<String, FracturedJsonError>;
+    type SerializeStructVariant = Impossible<String, FracturedJsonError>;
+
+    fn serialize_bool(self, v: bool) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<String> {
+        if v.is_finite() {
+            Ok(ryu::Buffer::new().format_finite(v).to_string())
+        } else {
+            Err(FracturedJsonError::Serialize(
+                "float key must be finite".to_string(),
+            ))
+        }
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<String> {
+        if v.is_finite() {
+            Ok(ryu::Buffer::new().format_finite(v).to_string())
+        } else {
+            Err(FracturedJsonError::Serialize(
+                "float key must be finite".to_string(),
+            ))
+        }
+    }
+
+    fn serialize_char(self, v: char) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<String> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_none(self) -> Result<String> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, _value: &T) -> Result<String> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_unit(self) -> Result<String> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<String> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<String> {
+        Ok(variant.to_string())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<String> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<String> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        Err(key_must_be_a_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Serialize;
+    use std::collections::BTreeMap;
+
+    fn compact() -> FracturedJsonOptions {
+        FracturedJsonOptions {
+            max_inline_complexity: 10,
+            max_total_line_length: 1000,
+            ..FracturedJsonOptions::default()
+        }
+    }
+
+    #[test]
+    fn test_enums_and_options() {
+        #[derive(Serialize)]
+        enum Shape {
+            Empty,
+            Circle(f64),
+            Line(i32, i32),
+            Rect { w: u8, h: u8 },
+        }
+
+        #[derive(Serialize)]
+        struct Doc {
+            shapes: Vec<Shape>,
+            label: Option<&'static str>,
+            missing: Option<u8>,
+            unit: (),
+        }
+
+        let doc = Doc {
+            shapes: vec![
+                Shape::Empty,
+                Shape::Circle(1.0),
+                Shape::Line(-1, 2),
+                Shape::Rect { w: 3, h: 4 },
+            ],
+            label: Some("a"),
+            missing: None,
+            unit: (),
+        };
+        let output = to_string_fractured(&doc, &compact()).unwrap();
+        assert_eq!(
+            output,
+            r#"{ "shapes": [ "Empty", { "Circle": 1.0 }, { "Line": [ -1, 2 ] }, { "Rect": { "w": 3, "h": 4 } } ], "label": "a", "missing": null, "unit": null }"#
+        );
+    }
+
+    #[test]
+    fn test_non_string_map_keys() {
+        let mut ints = BTreeMap::new();
+        ints.insert(2, "two");
+        ints.insert(-1, "minus one");
+        let output = to_string_fractured(&ints, &compact()).unwrap();
+        assert_eq!(output, r#"{ "-1": "minus one", "2": "two" }"#);
+
+        let mut bools = BTreeMap::new();
+        bools.insert(true, 1);
+        let output = to_string_fractured(&bools, &compact()).unwrap();
+        assert_eq!(output, r#"{ "true": 1 }"#);
+
+        let mut tuples = BTreeMap::new();
+        tuples.insert((1, 2), 3);
+        assert!(to_item(&tuples).is_err());
+    }
+
+    #[test]
+    fn test_floats_match_serde_json() {
+        let values = [0.1, 1.0, -2.5e-8, 1e300, f64::NAN];
+        let item = to_item(&values).unwrap();
+        let rendered: Vec<&str> = item.children.iter().map(|c| c.value.as_ref()).collect();
+        assert_eq!(rendered, ["0.1", "1.0", "-2.5e-8", "1e300", "null"]);
+    }
+
+    #[cfg(feature = "serde_json")]
+    #[test]
+    fn test_matches_value_conversion() {
+        let value = serde_json::json!({"a": [1, 2.5, "x", null], "b": {"c": false}});
+        assert_eq!(
+            to_item(&value).unwrap(),
+            crate::value::value_to_item(&value)
+        );
+    }
+}
@@ -0,0 +1,862 @@
+//! Low-memory formatting for very large inputs.
+//!
+//! The input is tokenized incrementally from an `io::BufRead`, and containers
+//! are buffered as `JsonItem`s only until their source text exceeds a window
+//! of `max_total_line_length` bytes. A container that fits in the window is
+//! laid out by the regular formatter, exactly as [`format_jsonc`] would; one
+//! that outgrows it (or holds comments) is written out expanded, one child
+//! per line, as the rest of it streams in. Memory use is therefore bounded by
+//! nesting depth times the window, not by document size, which makes huge
+//! top-level arrays of records formattable.
+//!
+//! Compact-array and table layouts need the whole container, so they only
+//! apply to containers that fit in the window. The same goes for
+//! `sort_keys`: larger objects keep their properties in source order.
+//! Comments before or after the root value are dropped, as they are there.
+//! Duplicate keys can't be checked without remembering every property name,
+//! so any `duplicate_key_policy` but `Keep` is rejected.
+//!
+//! [`format_jsonc`]: crate::format_jsonc
+
+use crate::error::{FracturedJsonError, Result};
+use crate::formatter::{escape_string, format_nested};
+use crate::options::{CommentPolicy, DuplicateKeyPolicy, FracturedJsonOptions};
+use crate::types::{JsonItem, JsonItemType};
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+
+/// Formats JSONC read from `reader` into `writer` with bounded memory.
+pub fn format_reader_low_memory<R: Read, W: Write + ?Sized>(
+    reader: R,
+    writer: &mut W,
+    options: &FracturedJsonOptions,
+) -> Result<()> {
+    if options.duplicate_key_policy != DuplicateKeyPolicy::Keep {
+        return Err(FracturedJsonError::InvalidOption(format!(
+            "duplicate_key_policy `{}` is not supported in low-memory mode",
+            options.duplicate_key_policy
+        )));
+    }
+    let mut emitter = Emitter {
+        lexer: Lexer::new(BufReader::new(reader)),
+        out: BufWriter::new(writer),
+        options,
+        stack: vec![Frame::new(Container::Root, None, 0)],
+        scratch: String::new(),
+    };
+    emitter.run()?;
+    emitter.out.flush()?;
+    Ok(())
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    BeginObject,
+    EndObject,
+    BeginArray,
+    EndArray,
+    Colon,
+    Comma,
+    String(String),
+    Number(String),
+    /// A bare word other than `true`, `false` or `null`, which may only be
+    /// a property name.
+    Word(String),
+    True,
+    False,
+    Null,
+    /// A comment, and whether it started on a line of its own.
+    Comment(String, bool),
+}
+
+/// Incremental JSONC tokenizer over a `BufRead`.
+struct Lexer<R: BufRead> {
+    reader: R,
+    offset: usize,
+    line: usize,
+    column: usize,
+    saw_newline: bool,
+}
+
+impl<R: BufRead> Lexer<R> {
+    fn new(reader: R) -> Self {
+        Lexer {
+            reader,
+            offset: 0,
+            line: 1,
+            column: 1,
+            saw_newline: true,
+        }
+    }
+
+    fn peek(&mut self) -> Result<Option<u8>> {
+        Ok(self.reader.fill_buf()?.first().copied())
+    }
+
+    fn bump(&mut self) -> Result<Option<u8>> {
+        let byte = self.peek()?;
+        if let Some(b) = byte {
+            self.reader.consume(1);
+            self.offset += 1;
+            if b == b'\n' {
+                self.line += 1;
+                self.column = 1;
+            } else if b & 0xC0 != 0x80 {
+                self.column += 1;
+            }
+        }
+        Ok(byte)
+    }
+
+    fn error(&self, message: impl Into<String>) -> FracturedJsonError {
+        FracturedJsonError::Syntax {
+            line: self.line,
+            column: self.column,
+            message: message.into(),
+        }
+    }
+
+    fn expect_byte(&mut self) -> Result<u8> {
+        self.bump()?
+            .ok_or_else(|| self.error("unexpected end of input"))
+    }
+
+    fn next_token(&mut self) -> Result<Option<Token>> {
+        loop {
+            let Some(byte) = self.peek()? else {
+                return Ok(None);
+            };
+            match byte {
+                b'\n' => {
+                    self.bump()?;
+                    self.saw_newline = true;
+                }
+                b' ' | b'\t' | b'\r' => {
+                    self.bump()?;
+                }
+                // UTF-8 byte order mark
+                0xEF if self.offset == 0 => {
+                    for expected in [0xEF, 0xBB, 0xBF] {
+                        if self.bump()? != Some(expected) {
+                            return Err(self.error("invalid byte order mark"));
+                        }
+                    }
+                }
+                _ => break,
+            }
+        }
+
+        let own_line = std::mem::replace(&mut self.saw_newline, false);
+        let byte = self.expect_byte()?;
+        let token = match byte {
+            b'{' => Token::BeginObject,
+            b'}' => Token::EndObject,
+            b'[' => Token::BeginArray,
+            b']' => Token::EndArray,
+            b':' => Token::Colon,
+            b',' => Token::Comma,
+            b'"' => Token::String(self.string()?),
+            b'/' => Token::Comment(self.comment()?, own_line),
+            b'-' | b'0'..=b'9' => {
+                let mut text = vec![byte];
+                while let Some(b @ (b'0'..=b'9' | b'.' | b'e' | b'E' | b'+' | b'-')) =
+                    self.peek()?
+                {
+                    text.push(b);
+                    self.bump()?;
+                }
+                let text = String::from_utf8(text).expect("ASCII digits");
+                if !is_json_number(&text) {
+                    return Err(self.error(format!("invalid number `{}`", text)));
+                }
+                Token::Number(text)
+            }
+            // Words are letters, digits and `-`, as in the regular parser
+            b'a'..=b'z' | b'A'..=b'Z' | 0x80.. => {
+                let mut word = vec![byte];
+                while let Some(b @ (b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'-' | 0x80..)) =
+                    self.peek()?
+                {
+                    word.push(b);
+                    self.bump()?;
+                }
+                let word = String::from_utf8(word)
+                    .ok()
+                    .filter(|w| w.chars().all(|c| c.is_alphanumeric() || c == '-'))
+                    .ok_or_else(|| self.error("unexpected character in word"))?;
+                match word.as_str() {
+                    "true" => Token::True,
+                    "false" => Token::False,
+                    "null" => Token::Null,
+                    _ => Token::Word(word),
+                }
+            }
+            _ => return Err(self.error(format!("unexpected character `{}`", byte as char))),
+        };
+        Ok(Some(token))
+    }
+
+    /// Reads the rest of a string literal, decoding escapes.
+    fn string(&mut self) -> Result<String> {
+        let mut bytes = Vec::new();
+        loop {
+            match self.expect_byte()? {
+                b'"' => break,
+                b'\\' => {
+                    let decoded = match self.expect_byte()? {
+                        b'"' => '"',
+                        b'\\' => '\\',
+                        b'/' => '/',
+                        b'b' => '\x08',
+                        b'f' => '\x0c',
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'u' => self.unicode_escape()?,
+                        other => {
+                            return Err(self.error(format!("invalid escape `\\{}`", other as char)))
+                        }
+                    };
+                    let mut utf8 = [0; 4];
+                    bytes.extend_from_slice(decoded.encode_utf8(&mut utf8).as_bytes());
+                }
+                b'\n' => return Err(self.error("unterminated string")),
+                b => bytes.push(b),
+            }
+        }
+        String::from_utf8(bytes).map_err(|_| self.error("invalid UTF-8 in string"))
+    }
+
+    fn hex4(&mut self) -> Result<u32> {
+        let mut value = 0;
+        for _ in 0..4 {
+            let digit = (self.expect_byte()? as char)
+                .to_digit(16)
+                .ok_or_else(|| self.error("invalid unicode escape"))?;
+            value = value * 16 + digit;
+        }
+        Ok(value)
+    }
+
+    fn unicode_escape(&mut self) -> Result<char> {
+        let high = self.hex4()?;
+        let code = if (0xD800..0xDC00).contains(&high) {
+            if self.expect_byte()? != b'\\' || self.expect_byte()? != b'u' {
+                return Err(self.error("unpaired surrogate in unicode escape"));
+            }
+            let low = self.hex4()?;
+            if !(0xDC00..0xE000).contains(&low) {
+                return Err(self.error("unpaired surrogate in unicode escape"));
+            }
+            0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+        } else {
+            high
+        };
+        char::from_u32(code).ok_or_else(|| self.error("invalid unicode escape"))
+    }
+
+    /// Reads a comment after its leading `/`, returning the full comment text.
+    fn comment(&mut self) -> Result<String> {
+        let mut bytes = vec![b'/'];
+        match self.expect_byte()? {
+            b'/' => {
+                bytes.push(b'/');
+                while let Some(b) = self.peek()? {
+                    if b == b'\n' {
+                        break;
+                    }
+                    bytes.push(b);
+                    self.bump()?;
+                }
+                if bytes.last() == Some(&b'\r') {
+                    bytes.pop();
+                }
+            }
+            b'*' => {
+                bytes.push(b'*');
+                loop {
+                    let b = self
+                        .bump()?
+                        .ok_or_else(|| self.error("unterminated block comment"))?;
+                    bytes.push(b);
+                    if b == b'/' && bytes.len() >= 4 && bytes[bytes.len() - 2] == b'*' {
+                        break;
+                    }
+                }
+            }
+            _ => return Err(self.error("unexpected character `/`")),
+        }
+        String::from_utf8(bytes).map_err(|_| self.error("invalid UTF-8 in comment"))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Container {
+    Root,
+    Array,
+    Object,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Expect {
+    Value,
+    Name,
+    Colon,
+    CommaOrEnd,
+}
+
+/// An open container.
+struct Frame {
+    kind: Container,
+    /// Input offset of the opening bracket.
+    start: usize,
+    /// This container's property name in its parent object.
+    name: Option<String>,
+    expect: Expect,
+    /// Property name read but whose value has not arrived yet.
+    pending_name: Option<String>,
+    /// Complete children held while the container is still buffered.
+    children: Vec<JsonItem<'static>>,
+    /// Whether the container's opening has been written out.
+    streaming: bool,
+    emitted: usize,
+    /// Comments on the same line as the last written child; they follow its
+    /// comma, which is only written once the next child arrives.
+    trailing_comments: Vec<String>,
+    /// Comments on their own lines, written before the next child.
+    leading_comments: Vec<String>,
+}
+
+impl Frame {
+    fn new(kind: Container, name: Option<String>, start: usize) -> Self {
+        Frame {
+            kind,
+            start,
+            name,
+            expect: if kind == Container::Object {
+                Expect::Name
+            } else {
+                Expect::Value
+            },
+            pending_name: None,
+            children: Vec::new(),
+            streaming: kind == Container::Root,
+            emitted: 0,
+            trailing_comments: Vec::new(),
+            leading_comments: Vec::new(),
+        }
+    }
+}
+
+struct Emitter<'o, R: BufRead, W: Write> {
+    lexer: Lexer<R>,
+    out: W,
+    options: &'o FracturedJsonOptions,
+    stack: Vec<Frame>,
+    scratch: String,
+}
+
+impl<R: BufRead, W: Write> Emitter<'_, R, W> {
+    fn run(&mut self) -> Result<()> {
+        while let Some(token) = self.lexer.next_token()? {
+            self.token(token)?;
+
+            // Spill outer containers first; an inner one gets a fresh window
+            while let Some(index) = self.first_buffered() {
+                if self.lexer.offset - self.stack[index].start <= self.options.max_total_line_length
+                {
+                    break;
+                }
+                self.spill(index)?;
+            }
+        }
+
+        if self.stack.len() > 1 {
+            return Err(self.lexer.error("unexpected end of input"));
+        }
+        if self.stack[0].emitted == 0 {
+            let null = JsonItem::new(JsonItemType::Null).with_value("null".to_string());
+            self.emit_child(0, null)?;
+        }
+        self.finish_frame(0)?;
+        if self.options.final_newline {
+            self.write(self.options.eol_string())?;
+        }
+        Ok(())
+    }
+
+    fn top(&mut self) -> &mut Frame {
+        self.stack.last_mut().expect("root frame")
+    }
+
+    fn token(&mut self, token: Token) -> Result<()> {
+        let frame = self.stack.last().expect("root frame");
+        match token {
+            // The regular formatter has nowhere to put comments outside the
+            // root value either, and drops them
+            Token::Comment(..) if frame.kind == Container::Root => Ok(()),
+            Token::Comment(text, own_line) => {
+                while let Some(index) = self.first_buffered() {
+                    self.spill(index)?;
+                }
+                let frame = self.top();
+                if own_line || frame.emitted == 0 || frame.pending_name.is_some() {
+                    frame.leading_comments.push(text);
+                } else {
+                    frame.trailing_comments.push(text);
+                }
+                Ok(())
+            }
+            Token::Colon => {
+                if frame.expect != Expect::Colon {
+                    return Err(self.lexer.error("unexpected `:`"));
+                }
+                self.top().expect = Expect::Value;
+                Ok(())
+            }
+            Token::Comma => {
+                if frame.expect != Expect::CommaOrEnd || frame.kind == Container::Root {
+                    return Err(self.lexer.error("unexpected `,`"));
+                }
+                let kind = frame.kind;
+                self.top().expect = if kind == Container::Object {
+                    Expect::Name
+                } else {
+                    Expect::Value
+                };
+                Ok(())
+            }
+            // Like the regular parser, accept words and numbers as names
+            Token::String(name) | Token::Word(name) | Token::Number(name)
+                if frame.expect == Expect::Name =>
+            {
+                let frame = self.top();
+                frame.pending_name = Some(name);
+                frame.expect = Expect::Colon;
+                Ok(())
+            }
+            Token::EndObject | Token::EndArray => {
+                let closes = if token == Token::EndObject {
+                    Container::Object
+                } else {
+                    Container::Array
+                };
+                // Trailing commas are accepted, as in the regular parser
+                let can_close = matches!(frame.expect, Expect::CommaOrEnd | Expect::Name)
+                    || (frame.expect == Expect::Value && frame.kind == Container::Array);
+                if frame.kind != closes || !can_close {
+                    return Err(self.lexer.error("unexpected closing bracket"));
+                }
+                self.close()
+            }
+            Token::BeginObject | Token::BeginArray => {
+                self.expect_value()?;
+                let kind = if token == Token::BeginObject {
+                    Container::Object
+                } else {
+                    Container::Array
+                };
+                let name = self.top().pending_name.take();
+                let start = self.lexer.offset - 1;
+                self.stack.push(Frame::new(kind, name, start));
+                Ok(())
+            }
+            Token::String(value) => self.scalar(JsonItemType::String, value),
+            Token::Number(value) => self.scalar(JsonItemType::Number, value),
+            Token::True => self.scalar(JsonItemType::True, "true".to_string()),
+            Token::False => self.scalar(JsonItemType::False, "false".to_string()),
+            Token::Null => self.scalar(JsonItemType::Null, "null".to_string()),
+            Token::Word(word) => Err(self.lexer.error(format!("unexpected word `{}`", word))),
+        }
+    }
+
+    fn expect_value(&mut self) -> Result<()> {
+        let frame = self.stack.last().expect("root frame");
+        let root_taken = frame.kind == Container::Root && frame.emitted > 0;
+        if frame.expect != Expect::Value || root_taken {
+            return Err(self.lexer.error("expected `,` or a closing bracket"));
+        }
+        Ok(())
+    }
+
+    fn scalar(&mut self, item_type: JsonItemType, value: String) -> Result<()> {
+        self.expect_value()?;
+        let item = JsonItem::new(item_type).with_value(value);
+        self.add_child(item)
+    }
+
+    /// Hands a complete child to the top container.
+    fn add_child(&mut self, mut item: JsonItem<'static>) -> Result<()> {
+        let index = self.stack.len() - 1;
+        let frame = self.top();
+        if let Some(name) = frame.pending_name.take() {
+            item.name = name.into();
+        }
+        frame.expect = Expect::CommaOrEnd;
+        if frame.streaming {
+            self.emit_child(index, item)
+        } else {
+            frame.children.push(item);
+            Ok(())
+        }
+    }
+
+    fn close(&mut self) -> Result<()> {
+        let index = self.stack.len() - 1;
+        if self.stack[index].streaming {
+            self.finish_frame(index)?;
+            self.stack.pop();
+            self.top().expect = Expect::CommaOrEnd;
+            return Ok(());
+        }
+
+        let frame = self.stack.pop().expect("open container");
+        let mut item = JsonItem::new(if frame.kind == Container::Object {
+            JsonItemType::Object
+        } else {
+            JsonItemType::Array
+        });
+        item.children = frame.children;
+        if let Some(name) = frame.name {
+            item.name = name.into();
+        }
+        self.add_child(item)
+    }
+
+    /// Index of the outermost container that is still being buffered.
+    fn first_buffered(&self) -> Option<usize> {
+        self.stack.iter().position(|frame| !frame.streaming)
+    }
+
+    /// Writes out the opening and buffered children of `stack[index]`, whose
+    /// parent is already streaming, and streams the rest of it.
+    fn spill(&mut self, index: usize) -> Result<()> {
+        let name = self.stack[index].name.clone();
+        self.begin_child(index - 1, name.as_deref())?;
+        let frame = &mut self.stack[index];
+        frame.streaming = true;
+        self.scratch.clear();
+        self.scratch.push(if frame.kind == Container::Object {
+            '{'
+        } else {
+            '['
+        });
+        let children = std::mem::take(&mut frame.children);
+        self.flush_scratch()?;
+        for child in children {
+            self.emit_child(index, child)?;
+        }
+        Ok(())
+    }
+
+    /// Indentation level of the children of `stack[index]`.
+    fn child_indent(index: usize) -> usize {
+        index
+    }
+
+    /// Writes the separator, pending comments, indentation and property name
+    /// that precede a new child of `stack[index]`.
+    fn begin_child(&mut self, index: usize, name: Option<&str>) -> Result<()> {
+        let indent = Self::child_indent(index);
+        let eol = self.options.eol_string();
+        let keep_comments = self.options.comment_policy == CommentPolicy::Preserve;
+        let is_root = self.stack[index].kind == Container::Root;
+        let frame = &mut self.stack[index];
+
+        self.scratch.clear();
+        if frame.emitted > 0 {
+            if !is_root {
+                self.scratch.push(',');
+            }
+            push_trailing_comments(
+                &mut self.scratch,
+                &mut frame.trailing_comments,
+                keep_comments,
+                self.options,
+            );
+            self.scratch.push_str(eol);
+        } else if !is_root {
+            // The opening bracket is on the previous line
+            self.scratch.push_str(eol);
+        }
+        for comment in frame.leading_comments.drain(..) {
+            if keep_comments {
+                push_indent(&mut self.scratch, self.options, indent);
+                self.scratch.push_str(&comment);
+                self.scratch.push_str(eol);
+            }
+        }
+        frame.emitted += 1;
+
+        push_indent(&mut self.scratch, self.options, indent);
+        if let Some(name) = name.filter(|_| frame.kind == Container::Object) {
+            self.scratch.push('"');
+            self.scratch.push_str(&escape_string(name));
+            self.scratch.push_str("\":");
+            if self.options.colon_padding {
+                self.scratch.push(' ');
+            }
+        }
+        self.flush_scratch()
+    }
+
+    fn emit_child(&mut self, index: usize, item: JsonItem<'static>) -> Result<()> {
+        self.begin_child(index, Some(&item.name))?;
+        self.scratch.clear();
+        format_nested(
+            &item,
+            self.options,
+            Self::child_indent(index),
+            &mut self.scratch,
+        );
+        self.flush_scratch()
+    }
+
+    /// Writes the comments left in `stack[index]` and its closing bracket.
+    fn finish_frame(&mut self, index: usize) -> Result<()> {
+        let eol = self.options.eol_string();
+        let keep_comments = self.options.comment_policy == CommentPolicy::Preserve;
+        let frame = &mut self.stack[index];
+        let is_root = frame.kind == Container::Root;
+
+        self.scratch.clear();
+        push_trailing_comments(
+            &mut self.scratch,
+            &mut frame.trailing_comments,
+            keep_comments,
+            self.options,
+        );
+        let has_leading = keep_comments && !frame.leading_comments.is_empty();
+        if has_leading || !is_root {
+            self.scratch.push_str(eol);
+        }
+        for comment in frame.leading_comments.drain(..) {
+            if keep_comments {
+                push_indent(&mut self.scratch, self.options, Self::child_indent(index));
+                self.scratch.push_str(&comment);
+                self.scratch.push_str(eol);
+            }
+        }
+        if !is_root {
+            push_indent(&mut self.scratch, self.options, index - 1);
+            self.scratch.push(if frame.kind == Container::Object {
+                '}'
+            } else {
+                ']'
+            });
+        } else if has_leading {
+            // The last comment line already ends the output
+            self.scratch.truncate(self.scratch.len() - eol.len());
+        }
+        self.flush_scratch()
+    }
+
+    fn flush_scratch(&mut self) -> Result<()> {
+        self.out.write_all(self.scratch.as_bytes())?;
+        Ok(())
+    }
+
+    fn write(&mut self, text: &str) -> io::Result<()> {
+        self.out.write_all(text.as_bytes())
+    }
+}
+
+fn push_indent(out: &mut String, options: &FracturedJsonOptions, indent: usize) {
+    if options.use_tab_to_indent {
+        out.extend(std::iter::repeat_n('\t', indent));
+    } else {
+        out.extend(std::iter::repeat_n(' ', indent * options.indent_spaces));
+    }
+}
+
+fn push_trailing_comments(
+    out: &mut String,
+    comments: &mut Vec<String>,
+    keep_comments: bool,
+    options: &FracturedJsonOptions,
+) {
+    for comment in comments.drain(..) {
+        if keep_comments {
+            out.push(' ');
+            if options.comment_padding {
+                out.push(' ');
+            }
+            out.push_str(&comment);
+        }
+    }
+}
+
+/// Whether `text` matches the JSON number grammar,
+/// `-?(0|[1-9][0-9]*)(\.[0-9]+)?([eE][+-]?[0-9]+)?`.
+fn is_json_number(text: &str) -> bool {
+    fn digits(bytes: &[u8]) -> usize {
+        bytes.iter().take_while(|b| b.is_ascii_digit()).count()
+    }
+
+    let mut rest = text.as_bytes();
+    if let [b'-', tail @ ..] = rest {
+        rest = tail;
+    }
+    match rest {
+        [b'0', tail @ ..] => rest = tail,
+        [b'1'..=b'9', ..] => rest = &rest[digits(rest)..],
+        _ => return false,
+    }
+    if let [b'.', tail @ ..] = rest {
+        let count = digits(tail);
+        if count == 0 {
+            return false;
+        }
+        rest = &tail[count..];
+    }
+    if let [b'e' | b'E', tail @ ..] = rest {
+        let tail = match tail {
+            [b'+' | b'-', signed @ ..] => signed,
+            _ => tail,
+        };
+        let count = digits(tail);
+        if count == 0 {
+            return false;
+        }
+        rest = &tail[count..];
+    }
+    rest.is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format_jsonc;
+
+    fn low_memory(input: &str, options: &FracturedJsonOptions) -> String {
+        let mut out = Vec::new();
+        format_reader_low_memory(input.as_bytes(), &mut out, options).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_small_documents_match_regular_formatting() {
+        let options = FracturedJsonOptions::default();
+        for input in [
+            r#"{"name": "test", "values": [1, 2, 3], "nested": {"ok": true}}"#,
+            r#"[{"x": 1, "y": 2}, {"x": 10, "y": 20}]"#,
+            r#""esc\"apedé""#,
+            r#"{name: 1, 2: "two", -1.5: null, café-au-lait: true}"#,
+            "42",
+            "[]",
+        ] {
+            assert_eq!(
+                low_memory(input, &options),
+                format_jsonc(input, &options).unwrap(),
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_root_comments_match_regular_formatting() {
+        let options = FracturedJsonOptions::default();
+        for input in [
+            "// head\n{\"a\": 1}",
+            "/* head */ [1, 2] // tail",
+            "{\"a\": [1, 2]}\n// tail\n",
+            "// only",
+        ] {
+            assert_eq!(
+                low_memory(input, &options),
+                format_jsonc(input, &options).unwrap(),
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_large_array_streams_records() {
+        let records: Vec<String> = (0..500)
+            .map(|i| format!(r#"{{"id": {}, "name": "row {}"}}"#, i, i))
+            .collect();
+        let input = format!("[{}]", records.join(",\n"));
+        let options = FracturedJsonOptions::default();
+        let output = low_memory(&input, &options);
+
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 502);
+        assert_eq!(lines[0], "[");
+        assert_eq!(lines[1], r#"    { "id": 0, "name": "row 0" },"#);
+        assert_eq!(lines[500], r#"    { "id": 499, "name": "row 499" }"#);
+        assert_eq!(lines[501], "]");
+
+        // The streamed output parses to the same document
+        let reparsed = jsonc_parser::parse_to_value(&output, &Default::default()).unwrap();
+        let original = jsonc_parser::parse_to_value(&input, &Default::default()).unwrap();
+        assert_eq!(reparsed, original);
+    }
+
+    #[test]
+    fn test_nested_spill_and_comments() {
+        let input = r#"{
+            // leading
+            "data": [1, 2, 3], // trailing
+            "big": {"a": "xxxxxxxxxxxxxxxxxxxx", "b": "yyyyyyyyyyyyyyyyyyyy"}
+        }"#;
+        let options = FracturedJsonOptions {
+            max_total_line_length: 40,
+            ..FracturedJsonOptions::default()
+        };
+        let output = low_memory(input, &options);
+        assert_eq!(
+            output,
+            [
+                "{",
+                "    // leading",
+                "    \"data\": [ 1, 2, 3 ],  // trailing",
+                "    \"big\": {",
+                "        \"a\": \"xxxxxxxxxxxxxxxxxxxx\",",
+                "        \"b\": \"yyyyyyyyyyyyyyyyyyyy\"",
+                "    }",
+                "}",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn test_syntax_errors() {
+        let options = FracturedJsonOptions::default();
+        for input in [
+            "[1, 2",
+            "{\"a\" 1}",
+            "[1 2]",
+            "{\"a\": tru}",
+            "1 2",
+            "[1}",
+            "[1.2.3]",
+            "[--4]",
+            "[01]",
+            "[1e]",
+            "[abc]",
+            "{a_b: 1}",
+        ] {
+            let mut out = Vec::new();
+            let result = format_reader_low_memory(input.as_bytes(), &mut out, &options);
+            assert!(
+                matches!(result, Err(FracturedJsonError::Syntax { .. })),
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_duplicate_key_policies_rejected() {
+        let options = FracturedJsonOptions {
+            duplicate_key_policy: DuplicateKeyPolicy::Error,
+            ..FracturedJsonOptions::default()
+        };
+        let mut out = Vec::new();
+        let result = format_reader_low_memory(r#"{"a": 1, "a": 2}"#.as_bytes(), &mut out, &options);
+        assert!(matches!(result, Err(FracturedJsonError::InvalidOption(_))));
+        assert!(out.is_empty());
+    }
+}
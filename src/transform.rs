@@ -1,265 +1,531 @@
-use crate::types::{JsonItem, JsonItemType};
-use jsonc_parser::cst::*;
+use crate::diagnostics::{Diagnostic, DiagnosticCode};
+use crate::error::{FracturedJsonError, Result};
+use crate::options::{CommentPolicy, DuplicateKeyPolicy, FracturedJsonOptions};
+use crate::types::{InputPosition, JsonItem, JsonItemType};
+use jsonc_parser::ast::{Array, Comment, Object, ObjectPropName, Value};
+use jsonc_parser::common::{Range, Ranged};
+use jsonc_parser::{
+    parse_to_ast, CollectOptions, CommentCollectionStrategy, ParseOptions, ParseResult,
+};
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::collections::HashMap;
 
-pub fn transform(cst: &CstRootNode) -> JsonItem {
-    match cst.root_value() {
-        Some(root_node) => transform_node(&root_node),
+/// Parses `input` into an AST with its comments collected, ready for
+/// [`transform`].
+pub fn parse(input: &str) -> Result<ParseResult<'_>> {
+    let collect_options = CollectOptions {
+        comments: CommentCollectionStrategy::Separate,
+        tokens: false,
+    };
+    Ok(parse_to_ast(
+        input,
+        &collect_options,
+        &ParseOptions::default(),
+    )?)
+}
+
+/// Builds the `JsonItem` tree for a parsed document. Strings, numbers and
+/// comments borrow from `input` unless they contained escapes.
+pub fn transform<'src>(input: &'src str, parsed: ParseResult<'src>) -> JsonItem<'src> {
+    transform_document(input, parsed, None)
+}
+
+/// Like [`transform`], also reporting comments that were dropped or moved
+/// to `diagnostics` when it is given.
+fn transform_document<'src>(
+    input: &'src str,
+    parsed: ParseResult<'src>,
+    diagnostics: Option<&mut Vec<Diagnostic>>,
+) -> JsonItem<'src> {
+    let source = Source {
+        input,
+        comments: Comments::new(input, parsed.comments.as_ref()),
+        positions: Positions::new(input),
+    };
+    let item = match parsed.value {
+        Some(value) => transform_value(value, &source),
         None => JsonItem::new(JsonItemType::Null),
+    };
+
+    if let Some(diagnostics) = diagnostics {
+        // The comments are in order, so one pass converts their offsets
+        let positions = Positions::new(input);
+        for comment in &source.comments.all {
+            let (code, message) = match comment.placement.get() {
+                Placement::Kept => continue,
+                Placement::Dropped => (
+                    DiagnosticCode::CommentDropped,
+                    "comment dropped; there is no place for it in the output",
+                ),
+                Placement::Moved => (
+                    DiagnosticCode::CommentMoved,
+                    "comment at the end of a line moved in front of the next item",
+                ),
+            };
+            let position = positions.at(comment.range.start);
+            diagnostics.push(Diagnostic::warning(code, position, message));
+        }
     }
+    item
 }
 
-fn transform_node(node: &CstNode) -> JsonItem {
-    match node {
-        CstNode::Leaf(leaf) => transform_leaf(leaf),
-        CstNode::Container(container) => transform_container(container),
+/// Parses and transforms `input`, then applies the options that act on the
+/// tree itself, such as `duplicate_key_policy`. Returns the warnings found
+/// along the way.
+pub fn parse_document<'src>(
+    input: &'src str,
+    options: &FracturedJsonOptions,
+) -> Result<(JsonItem<'src>, Vec<Diagnostic>)> {
+    let mut diagnostics = Vec::new();
+    // Comments removed by the options are dropped on purpose
+    let comment_diagnostics = options.comment_policy == CommentPolicy::Preserve;
+    let mut item = transform_document(
+        input,
+        parse(input)?,
+        comment_diagnostics.then_some(&mut diagnostics),
+    );
+    if options.duplicate_key_policy != DuplicateKeyPolicy::Keep {
+        apply_duplicate_key_policy(&mut item, options.duplicate_key_policy, &mut diagnostics)?;
     }
+    Ok((item, diagnostics))
 }
 
-fn transform_leaf(leaf: &CstLeafNode) -> JsonItem {
-    match leaf {
-        CstLeafNode::StringLit(s) => {
-            // Decoded strings need to be owned (escape sequences processed)
-            let value = s.decoded_value().unwrap_or_default();
-            JsonItem::new(JsonItemType::String).with_value(value)
-        }
-        CstLeafNode::NumberLit(n) => {
-            // Numbers must use Display - jsonc-parser doesn't expose raw text access
-            JsonItem::new(JsonItemType::Number).with_value(n.to_string())
-        }
-        CstLeafNode::BooleanLit(b) => {
-            // Use static strings for literals
-            let value = if b.value() { "true" } else { "false" };
-            JsonItem::new(if b.value() {
-                JsonItemType::True
-            } else {
-                JsonItemType::False
-            })
-            .with_value(value.to_string())
+/// What transforming a value needs to know about the rest of the input.
+struct Source<'src> {
+    input: &'src str,
+    comments: Comments<'src>,
+    positions: Positions<'src>,
+}
+
+/// Converts byte offsets, visited in increasing order, to line and column.
+struct Positions<'src> {
+    input: &'src str,
+    /// The last offset converted and its position.
+    last: Cell<(usize, InputPosition)>,
+}
+
+impl<'src> Positions<'src> {
+    fn new(input: &'src str) -> Self {
+        Positions {
+            input,
+            last: Cell::new((0, InputPosition { line: 1, column: 1 })),
         }
-        CstLeafNode::NullKeyword(_) => {
-            // Use static string for null
-            JsonItem::new(JsonItemType::Null).with_value("null".to_string())
+    }
+
+    fn at(&self, offset: usize) -> InputPosition {
+        let (mut from, mut position) = self.last.get();
+        if offset < from {
+            (from, position) = (0, InputPosition { line: 1, column: 1 });
         }
-        CstLeafNode::Comment(c) => {
-            // Comments need owned strings (from CST Display)
-            let comment_text = c.to_string();
-            let is_line_comment = comment_text.starts_with("//");
-            let item_type = if is_line_comment {
-                JsonItemType::LineComment
+        for ch in self.input[from..offset].chars() {
+            if ch == '\n' {
+                position.line += 1;
+                position.column = 1;
             } else {
-                JsonItemType::BlockComment
-            };
-            JsonItem::new(item_type).with_value(comment_text)
-        }
-        // Skip whitespace, newlines
-        CstLeafNode::Whitespace(_) | CstLeafNode::Newline(_) => {
-            JsonItem::new(JsonItemType::BlankLine)
+                position.column += 1;
+            }
         }
-        _ => JsonItem::new(JsonItemType::Null),
+        self.last.set((offset, position));
+        position
     }
 }
 
-fn transform_container(container: &CstContainerNode) -> JsonItem {
-    match container {
-        CstContainerNode::Array(array) => transform_array(array),
-        CstContainerNode::Object(object) => transform_object(object),
-        _ => JsonItem::new(JsonItemType::Null),
-    }
+/// A comment's full source text, including its delimiters.
+struct CommentText<'src> {
+    range: Range,
+    text: &'src str,
+    is_line: bool,
+    placement: Cell<Placement>,
 }
 
-fn transform_array(array: &CstArray) -> JsonItem {
-    let mut json_item = JsonItem::new(JsonItemType::Array);
-    let mut pending_prefix_comment: Option<String> = None;
-    let mut pending_prefix_is_line_style: Option<bool> = None;
-
-    for child in array.children() {
-        // Skip tokens (brackets, commas, colons, etc.)
-        if child.is_token() {
-            continue;
-        }
+/// Where a comment ended up in the tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Placement {
+    Dropped,
+    Kept,
+    /// Attached to an item other than the one it was written next to.
+    Moved,
+}
 
-        // Skip whitespace and newlines (but not comments)
-        if child.is_whitespace() || child.is_newline() {
-            continue;
-        }
+/// Every comment in the document, ordered by position.
+struct Comments<'src> {
+    all: Vec<CommentText<'src>>,
+}
 
-        match child {
-            CstNode::Leaf(leaf) => {
-                match leaf {
-                    CstLeafNode::Comment(c) => {
-                        let comment_text = c.to_string();
-                        let is_line_comment = comment_text.starts_with("//");
-
-                        // Store as pending prefix comment for next element
-                        pending_prefix_comment = Some(comment_text);
-                        pending_prefix_is_line_style = Some(is_line_comment);
-                    }
-                    _ => {
-                        let mut item = transform_leaf(&leaf);
-
-                        // Attach pending prefix comment if available
-                        attach_pending_comment(
-                            &mut item,
-                            &mut pending_prefix_comment,
-                            &mut pending_prefix_is_line_style,
-                        );
-
-                        json_item.children.push(item);
-                    }
+impl<'src> Comments<'src> {
+    fn new(input: &'src str, map: Option<&jsonc_parser::CommentMap<'src>>) -> Self {
+        let mut all: Vec<CommentText<'src>> = map
+            .into_iter()
+            .flat_map(|map| map.values())
+            .flat_map(|comments| comments.iter())
+            .map(|comment| {
+                let range = comment.range();
+                CommentText {
+                    range,
+                    // Line comments run up to, but not including, the newline
+                    text: input[range.start..range.end].trim_end_matches('\r'),
+                    is_line: matches!(comment, Comment::Line(_)),
+                    placement: Cell::new(Placement::Dropped),
                 }
-            }
-            CstNode::Container(container) => {
-                let mut item = transform_container(&container);
+            })
+            .collect();
+        // The map lists each comment under both neighbouring tokens
+        all.sort_by_key(|c| c.range.start);
+        all.dedup_by_key(|c| c.range.start);
+        Comments { all }
+    }
 
-                // Attach pending prefix comment if available
-                attach_pending_comment(
-                    &mut item,
-                    &mut pending_prefix_comment,
-                    &mut pending_prefix_is_line_style,
-                );
+    /// Comments lying entirely between byte offsets `start` and `end`.
+    fn between(&self, start: usize, end: usize) -> &[CommentText<'src>] {
+        let first = self.all.partition_point(|c| c.range.start < start);
+        let last = self.all.partition_point(|c| c.range.start < end);
+        &self.all[first..last.max(first)]
+    }
+}
 
-                json_item.children.push(item);
+fn transform_value<'src>(value: Value<'src>, source: &Source<'src>) -> JsonItem<'src> {
+    let position = source.positions.at(value.range().start);
+    let mut item = match value {
+        Value::StringLit(s) => JsonItem::new(JsonItemType::String).with_value(s.value),
+        Value::NumberLit(n) => JsonItem::new(JsonItemType::Number).with_value(n.value),
+        Value::BooleanLit(b) => {
+            if b.value {
+                JsonItem::new(JsonItemType::True).with_value("true")
+            } else {
+                JsonItem::new(JsonItemType::False).with_value("false")
             }
         }
+        Value::NullKeyword(_) => JsonItem::new(JsonItemType::Null).with_value("null"),
+        Value::Array(array) => transform_array(array, source),
+        Value::Object(object) => transform_object(object, source),
+    };
+    item.input_position = position;
+    item
+}
+
+fn comment_item<'src>(comment: &CommentText<'src>) -> JsonItem<'src> {
+    comment.placement.set(Placement::Kept);
+    let item_type = if comment.is_line {
+        JsonItemType::LineComment
+    } else {
+        JsonItemType::BlockComment
+    };
+    JsonItem::new(item_type).with_value(comment.text)
+}
+
+/// Attaches the last comment between `gap_start` and the item at
+/// `item_start` (the one nearest the item) as its prefix.
+fn attach_prefix_comment<'src>(
+    item: &mut JsonItem<'src>,
+    source: &Source<'src>,
+    gap_start: usize,
+    item_start: usize,
+    follows_sibling: bool,
+) {
+    if let Some(comment) = source.comments.between(gap_start, item_start).last() {
+        item.prefix_comment = Some(Cow::Borrowed(comment.text));
+        item.is_post_comment_line_style = comment.is_line;
+        let moved = follows_sibling && ends_line(source.input, gap_start, comment, item_start);
+        comment.placement.set(if moved {
+            Placement::Moved
+        } else {
+            Placement::Kept
+        });
     }
+}
 
-    // Handle standalone comments at the end of array
-    // (But skip if they're just whitespace/comment artifacts with no value)
-    let has_non_comment_items = json_item
-        .children
-        .iter()
-        .any(|c| !c.item_type.is_comment_or_blank());
-    if has_non_comment_items || json_item.children.is_empty() {
-        if let Some(comment) = pending_prefix_comment.take() {
-            let item_type = if comment.starts_with("//") {
-                JsonItemType::LineComment
-            } else {
-                JsonItemType::BlockComment
-            };
-            let comment_item = JsonItem::new(item_type).with_value(comment);
-            json_item.children.push(comment_item);
+/// Whether `comment` trails whatever ends at `gap_start`, on the same line,
+/// with the next token at `next_start` on a later one.
+fn ends_line(input: &str, gap_start: usize, comment: &CommentText, next_start: usize) -> bool {
+    !input[gap_start..comment.range.start].contains('\n')
+        && input[comment.range.end..next_start].contains('\n')
+}
+
+fn transform_array<'src>(array: Array<'src>, source: &Source<'src>) -> JsonItem<'src> {
+    let comments = &source.comments;
+    let mut json_item = JsonItem::new(JsonItemType::Array);
+    json_item.children.reserve(array.elements.len());
+
+    // Skip the opening bracket
+    let mut gap_start = array.range.start + 1;
+    for element in array.elements {
+        let range = element.range();
+        let follows_sibling = !json_item.children.is_empty();
+        let mut item = transform_value(element, source);
+        attach_prefix_comment(&mut item, source, gap_start, range.start, follows_sibling);
+        json_item.children.push(item);
+        gap_start = range.end;
+    }
+
+    // A comment after the last element is kept as a standalone item
+    if let Some(comment) = comments.between(gap_start, array.range.end).last() {
+        json_item.children.push(comment_item(comment));
+        // Written on a line of its own
+        if gap_start > array.range.start + 1
+            && ends_line(source.input, gap_start, comment, array.range.end)
+        {
+            comment.placement.set(Placement::Moved);
         }
     }
 
     json_item
 }
 
-fn transform_object(object: &CstObject) -> JsonItem {
+fn transform_object<'src>(object: Object<'src>, source: &Source<'src>) -> JsonItem<'src> {
+    let comments = &source.comments;
     let mut json_item = JsonItem::new(JsonItemType::Object);
 
-    // Transform all properties and extract comments from within ObjectProp
-    for prop in object.properties() {
-        let name = prop
-            .name()
-            .and_then(|n| n.decoded_value().ok())
-            .unwrap_or_default();
-        let mut value = match prop.value() {
-            Some(v) => transform_node(&v),
-            None => JsonItem::new(JsonItemType::Null),
+    // Without properties, every comment is kept as a standalone item
+    if object.properties.is_empty() {
+        json_item.children = comments
+            .between(object.range.start + 1, object.range.end)
+            .iter()
+            .map(comment_item)
+            .collect();
+        return json_item;
+    }
+
+    json_item.children.reserve(object.properties.len());
+    let mut gap_start = object.range.start + 1;
+    for prop in object.properties {
+        let prop_range = prop.range;
+        let (name_end, name) = match prop.name {
+            ObjectPropName::String(s) => (s.range.end, s.value),
+            ObjectPropName::Word(w) => (w.range.end, Cow::Borrowed(w.value)),
         };
+        // A property is located by its name
+        let position = source.positions.at(prop_range.start);
+        let value_start = prop.value.range().start;
+        let mut value = transform_value(prop.value, source);
+        value.input_position = position;
 
-        // Extract comments from ObjectProp children (middle and postfix comments)
-        let mut found_middle_comment = false;
-        for prop_child in prop.children() {
-            // Skip tokens and whitespace
-            if prop_child.is_token() || prop_child.is_whitespace() || prop_child.is_newline() {
+        // Comments between the name and the value: the first is the middle
+        // comment, any later one the postfix comment
+        let mut inner = comments.between(name_end, value_start).iter();
+        if let Some(middle) = inner.next() {
+            value.middle_comment = Some(Cow::Borrowed(middle.text));
+            middle.placement.set(Placement::Kept);
+        }
+        if let Some(postfix) = inner.last() {
+            value.postfix_comment = Some(Cow::Borrowed(postfix.text));
+            value.is_post_comment_line_style = postfix.is_line;
+            postfix.placement.set(Placement::Kept);
+        }
+
+        let follows_sibling = !json_item.children.is_empty();
+        attach_prefix_comment(
+            &mut value,
+            source,
+            gap_start,
+            prop_range.start,
+            follows_sibling,
+        );
+        gap_start = prop_range.end;
+
+        json_item.children.push(value.with_name(name));
+    }
+
+    json_item
+}
+
+/// Applies `policy` to every object below `item`.
+fn apply_duplicate_key_policy(
+    item: &mut JsonItem<'_>,
+    policy: DuplicateKeyPolicy,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Result<()> {
+    if item.item_type == JsonItemType::Object {
+        // Index of the first property with each name
+        let mut first: HashMap<&str, usize> = HashMap::new();
+        // (duplicate, first) index pairs
+        let mut duplicates = Vec::new();
+        for (index, child) in item.children.iter().enumerate() {
+            if child.item_type.is_comment_or_blank() {
                 continue;
             }
+            match first.get(child.name.as_ref()) {
+                Some(&original) => duplicates.push((index, original)),
+                None => {
+                    first.insert(&child.name, index);
+                }
+            }
+        }
+        drop(first);
 
-            // Check for comments within the property
-            if let Some(c) = as_comment(&prop_child) {
-                let comment_text = c.to_string();
-                let is_line = comment_text.starts_with("//");
-                // If we haven't found value yet, this is a middle comment
-                // Otherwise, it's a postfix comment
-                if !found_middle_comment {
-                    value.middle_comment = Some(comment_text);
-                    found_middle_comment = true;
-                } else {
-                    value.postfix_comment = Some(comment_text);
-                    value.is_post_comment_line_style = is_line;
+        for &(index, original) in &duplicates {
+            let duplicate = &item.children[index];
+            match policy {
+                DuplicateKeyPolicy::Error => {
+                    return Err(FracturedJsonError::DuplicateKey {
+                        key: duplicate.name.to_string(),
+                        line: duplicate.input_position.line,
+                        column: duplicate.input_position.column,
+                    })
                 }
+                DuplicateKeyPolicy::Warn => {
+                    let first = item.children[original].input_position;
+                    diagnostics.push(Diagnostic::warning(
+                        DiagnosticCode::DuplicateKey,
+                        duplicate.input_position,
+                        format!(
+                            "duplicate key `{}` (first at line {}, column {})",
+                            duplicate.name, first.line, first.column
+                        ),
+                    ));
+                }
+                DuplicateKeyPolicy::KeepLast => item.children.swap(index, original),
+                DuplicateKeyPolicy::Keep | DuplicateKeyPolicy::KeepFirst => {}
             }
         }
 
-        // Only add property if it has a name
-        if !name.is_empty() {
-            json_item.children.push(value.with_name(name));
+        if matches!(
+            policy,
+            DuplicateKeyPolicy::KeepFirst | DuplicateKeyPolicy::KeepLast
+        ) && !duplicates.is_empty()
+        {
+            let mut keep = vec![true; item.children.len()];
+            for &(index, _) in &duplicates {
+                keep[index] = false;
+            }
+            let mut keep = keep.into_iter();
+            item.children.retain(|_| keep.next().unwrap_or(true));
         }
     }
 
-    // Check if object has any properties
-    let has_properties = !json_item.children.is_empty();
+    for child in &mut item.children {
+        apply_duplicate_key_policy(child, policy, diagnostics)?;
+    }
+    Ok(())
+}
 
-    // Now, iterate over children to find and attach prefix/postfix comments
-    let mut pending_prefix_comment: Option<String> = None;
-    let mut pending_prefix_is_line_style: Option<bool> = None;
-    let mut current_prop_index: usize = 0;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    for child in object.children() {
-        // Skip whitespace and newlines
-        if child.is_whitespace() || child.is_newline() {
-            continue;
-        }
+    fn parse_items(input: &str) -> JsonItem<'_> {
+        transform(input, parse(input).unwrap())
+    }
 
-        // Check for comment
-        if let Some(c) = as_comment(&child) {
-            let comment_text = c.to_string();
-            let is_line_comment = comment_text.starts_with("//");
+    #[test]
+    fn test_borrows_unescaped_text() {
+        let input = r#"{"plain": "text", "esc\"aped": "a\nb", "n": 1.50}"#;
+        let root = parse_items(input);
 
-            // If we have properties, treat as prefix comment for next property
-            if has_properties {
-                pending_prefix_comment = Some(comment_text);
-                pending_prefix_is_line_style = Some(is_line_comment);
-            } else {
-                // No properties - preserve as standalone comment
-                let item_type = if comment_text.starts_with("//") {
-                    JsonItemType::LineComment
-                } else {
-                    JsonItemType::BlockComment
-                };
-                let comment_item = JsonItem::new(item_type).with_value(comment_text);
-                json_item.children.push(comment_item);
-            }
-        }
+        let plain = &root.children[0];
+        assert!(matches!(plain.name, Cow::Borrowed("plain")));
+        assert!(matches!(plain.value, Cow::Borrowed("text")));
 
-        // Check if it's an ObjectProp - marks a property, attach any pending comment
-        if let CstNode::Container(CstContainerNode::ObjectProp(_)) = child {
-            if current_prop_index < json_item.children.len() {
-                attach_pending_comment(
-                    &mut json_item.children[current_prop_index],
-                    &mut pending_prefix_comment,
-                    &mut pending_prefix_is_line_style,
-                );
-            }
-            current_prop_index += 1;
-        }
+        let escaped = &root.children[1];
+        assert!(matches!(&escaped.name, Cow::Owned(name) if name == "esc\"aped"));
+        assert!(matches!(&escaped.value, Cow::Owned(value) if value == "a\nb"));
 
-        // Skip tokens (after handling properties and comments)
-        if child.is_token() {
-            continue;
-        }
+        assert!(matches!(root.children[2].value, Cow::Borrowed("1.50")));
     }
 
-    json_item
-}
+    #[test]
+    fn test_comment_attachment() {
+        let input = "{\n  // lead\n  \"a\": /* mid */ 1,\n  \"b\": [\n    /* first */ 1,\n    2\n    // end\n  ]\n}";
+        let root = parse_items(input);
 
-fn attach_pending_comment(
-    item: &mut JsonItem,
-    pending_prefix_comment: &mut Option<String>,
-    pending_prefix_is_line_style: &mut Option<bool>,
-) {
-    if let Some(comment) = pending_prefix_comment.take() {
-        item.prefix_comment = Some(comment);
-        if let Some(is_line) = pending_prefix_is_line_style.take() {
-            item.is_post_comment_line_style = is_line;
-        }
+        let a = &root.children[0];
+        assert_eq!(a.prefix_comment.as_deref(), Some("// lead"));
+        assert!(a.is_post_comment_line_style);
+        assert_eq!(a.middle_comment.as_deref(), Some("/* mid */"));
+
+        let b = &root.children[1];
+        assert_eq!(b.children[0].prefix_comment.as_deref(), Some("/* first */"));
+        assert_eq!(b.children[2].item_type, JsonItemType::LineComment);
+        assert_eq!(b.children[2].value, "// end");
+    }
+
+    #[test]
+    fn test_empty_property_name_is_kept() {
+        let root = parse_items(r#"{"": 1, "a": 2}"#);
+        assert_eq!(root.children.len(), 2);
+        assert_eq!(root.children[0].name, "");
+    }
+
+    #[test]
+    fn test_duplicate_key_policies() {
+        let input = "{\n  \"a\": 1,\n  \"b\": {\"x\": 1, \"x\": 2},\n  \"a\": 3\n}";
+        let document = |policy| {
+            let options = FracturedJsonOptions {
+                duplicate_key_policy: policy,
+                ..FracturedJsonOptions::default()
+            };
+            parse_document(input, &options)
+        };
+        let values = |item: &JsonItem| -> Vec<String> {
+            item.children
+                .iter()
+                .map(|c| format!("{}={}", c.name, c.value))
+                .collect()
+        };
+
+        let (root, diagnostics) = document(DuplicateKeyPolicy::Keep).unwrap();
+        assert_eq!(root.children.len(), 3);
+        assert!(diagnostics.is_empty());
+
+        let (root, _) = document(DuplicateKeyPolicy::KeepFirst).unwrap();
+        assert_eq!(values(&root), ["a=1", "b="]);
+        assert_eq!(values(&root.children[1]), ["x=1"]);
+
+        let (root, _) = document(DuplicateKeyPolicy::KeepLast).unwrap();
+        assert_eq!(values(&root), ["a=3", "b="]);
+        assert_eq!(values(&root.children[1]), ["x=2"]);
+
+        let (root, diagnostics) = document(DuplicateKeyPolicy::Warn).unwrap();
+        assert_eq!(root.children.len(), 3);
+        let messages: Vec<String> = diagnostics.iter().map(|d| d.to_string()).collect();
+        assert_eq!(
+            messages,
+            [
+                "4:3: warning[duplicate-key]: duplicate key `a` (first at line 2, column 3)",
+                "3:17: warning[duplicate-key]: duplicate key `x` (first at line 3, column 9)",
+            ]
+        );
+
+        assert!(matches!(
+            document(DuplicateKeyPolicy::Error),
+            Err(FracturedJsonError::DuplicateKey { ref key, line: 4, column: 3 }) if key == "a"
+        ));
+    }
+
+    #[test]
+    fn test_comment_diagnostics() {
+        let input = "// header\n{\n  \"a\": 1, // about a\n  \"b\": [1 /* x */, 2],\n  /* one */ /* two */ \"c\": 3\n  // trailing\n}";
+        let (_, diagnostics) = parse_document(input, &FracturedJsonOptions::default()).unwrap();
+        let messages: Vec<String> = diagnostics.iter().map(|d| d.to_string()).collect();
+        assert_eq!(
+            messages,
+            [
+                "1:1: warning[comment-dropped]: comment dropped; there is no place for it in the output",
+                "3:11: warning[comment-moved]: comment at the end of a line moved in front of the next item",
+                "5:3: warning[comment-dropped]: comment dropped; there is no place for it in the output",
+                "6:3: warning[comment-dropped]: comment dropped; there is no place for it in the output",
+            ]
+        );
+
+        let options = FracturedJsonOptions {
+            comment_policy: CommentPolicy::Remove,
+            ..FracturedJsonOptions::default()
+        };
+        assert!(parse_document(input, &options).unwrap().1.is_empty());
+    }
+
+    #[test]
+    fn test_crlf_line_comment() {
+        let root = parse_items("[\r\n  // note\r\n  1\r\n]");
+        assert_eq!(root.children[0].prefix_comment.as_deref(), Some("// note"));
     }
-}
 
-fn as_comment(node: &CstNode) -> Option<&CstComment> {
-    match node {
-        CstNode::Leaf(CstLeafNode::Comment(c)) => Some(c),
-        _ => None,
+    #[test]
+    fn test_into_owned() {
+        let input = String::from(r#"{"k": ["v"]}"#);
+        let owned: JsonItem<'static> = parse_items(&input).into_owned();
+        drop(input);
+        assert_eq!(owned.children[0].name, "k");
+        assert_eq!(owned.children[0].children[0].value, "v");
     }
 }
@@ -1,3 +1,5 @@
+use std::borrow::Cow;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum JsonItemType {
     Null,
@@ -44,32 +46,35 @@ impl JsonItemType {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Default)]
+/// A 1-based line and column (in characters) in the input text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub struct InputPosition {
     pub line: usize,
     pub column: usize,
 }
 
+/// A parsed JSON element. Names, values and comments borrow from the input
+/// text where no unescaping was needed.
 #[derive(Debug, Clone, PartialEq)]
-pub struct JsonItem {
+pub struct JsonItem<'src> {
     pub item_type: JsonItemType,
     pub input_position: InputPosition,
-    pub name: String,
-    pub value: String,
-    pub prefix_comment: Option<String>,
-    pub middle_comment: Option<String>,
-    pub postfix_comment: Option<String>,
+    pub name: Cow<'src, str>,
+    pub value: Cow<'src, str>,
+    pub prefix_comment: Option<Cow<'src, str>>,
+    pub middle_comment: Option<Cow<'src, str>>,
+    pub postfix_comment: Option<Cow<'src, str>>,
     pub is_post_comment_line_style: bool,
-    pub children: Vec<JsonItem>,
+    pub children: Vec<JsonItem<'src>>,
 }
 
-impl JsonItem {
+impl<'src> JsonItem<'src> {
     pub fn new(item_type: JsonItemType) -> Self {
         JsonItem {
             item_type,
             input_position: InputPosition::default(),
-            name: String::new(),
-            value: String::new(),
+            name: Cow::Borrowed(""),
+            value: Cow::Borrowed(""),
             prefix_comment: None,
             middle_comment: None,
             postfix_comment: None,
@@ -79,13 +84,13 @@ impl JsonItem {
     }
 
     /// Create a JsonItem with a string value.
-    pub fn with_value(mut self, value: String) -> Self {
-        self.value = value;
+    pub fn with_value(mut self, value: impl Into<Cow<'src, str>>) -> Self {
+        self.value = value.into();
         self
     }
 
-    pub fn with_name(mut self, name: String) -> Self {
-        self.name = name;
+    pub fn with_name(mut self, name: impl Into<Cow<'src, str>>) -> Self {
+        self.name = name.into();
         self
     }
 
@@ -101,4 +106,23 @@ impl JsonItem {
             _ => false,
         }
     }
+
+    /// Copies any borrowed text so the item no longer depends on the input.
+    pub fn into_owned(self) -> JsonItem<'static> {
+        JsonItem {
+            item_type: self.item_type,
+            input_position: self.input_position,
+            name: Cow::Owned(self.name.into_owned()),
+            value: Cow::Owned(self.value.into_owned()),
+            prefix_comment: self.prefix_comment.map(|c| Cow::Owned(c.into_owned())),
+            middle_comment: self.middle_comment.map(|c| Cow::Owned(c.into_owned())),
+            postfix_comment: self.postfix_comment.map(|c| Cow::Owned(c.into_owned())),
+            is_post_comment_line_style: self.is_post_comment_line_style,
+            children: self
+                .children
+                .into_iter()
+                .map(JsonItem::into_owned)
+                .collect(),
+        }
+    }
 }
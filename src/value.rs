@@ -0,0 +1,78 @@
+//! Formatting of `serde_json` values.
+//!
+//! The value tree is converted straight into a [`JsonItem`] tree, skipping the
+//! serialize-to-text and reparse steps.
+
+use crate::formatter::format;
+use crate::options::FracturedJsonOptions;
+use crate::types::{JsonItem, JsonItemType};
+use serde_json::Value;
+
+/// Builds the `JsonItem` tree for a `serde_json::Value`, borrowing its
+/// strings.
+pub fn value_to_item(value: &Value) -> JsonItem<'_> {
+    match value {
+        Value::Null => JsonItem::new(JsonItemType::Null).with_value("null"),
+        Value::Bool(true) => JsonItem::new(JsonItemType::True).with_value("true"),
+        Value::Bool(false) => JsonItem::new(JsonItemType::False).with_value("false"),
+        Value::Number(n) => JsonItem::new(JsonItemType::Number).with_value(n.to_string()),
+        Value::String(s) => JsonItem::new(JsonItemType::String).with_value(s.as_str()),
+        Value::Array(values) => {
+            let mut item = JsonItem::new(JsonItemType::Array);
+            item.children = values.iter().map(value_to_item).collect();
+            item
+        }
+        Value::Object(map) => {
+            let mut item = JsonItem::new(JsonItemType::Object);
+            item.children = map
+                .iter()
+                .map(|(name, value)| value_to_item(value).with_name(name.as_str()))
+                .collect();
+            item
+        }
+    }
+}
+
+/// Formats a `serde_json::Value`.
+pub fn format_value(value: &Value, options: &FracturedJsonOptions) -> String {
+    format(&value_to_item(value), options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format_jsonc;
+    use crate::ser::to_string_fractured;
+    use serde::Serialize;
+    use serde_json::json;
+
+    #[test]
+    fn test_format_value_matches_text_pipeline() {
+        let value = json!({
+            "name": "Alice",
+            "tags": ["a", "b\n"],
+            "nested": {"x": 1.5, "y": null, "ok": true},
+            "quote\"key": -3
+        });
+        let options = FracturedJsonOptions::default();
+        let text = serde_json::to_string(&value).unwrap();
+        assert_eq!(
+            format_value(&value, &options),
+            format_jsonc(&text, &options).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_to_string_fractured() {
+        #[derive(Serialize)]
+        struct Point {
+            x: i32,
+            y: i32,
+        }
+
+        let points = vec![Point { x: 1, y: 2 }, Point { x: 3, y: 4 }];
+        let output = to_string_fractured(&points, &FracturedJsonOptions::default()).unwrap();
+        let parsed: Value = serde_json::from_str(&output).unwrap();
+        assert_eq!(parsed, json!([{"x": 1, "y": 2}, {"x": 3, "y": 4}]));
+    }
+}